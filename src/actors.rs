@@ -5,12 +5,13 @@ pub mod player;
 pub mod power;
 
 pub use crate::screen::*;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 /// Character ID for mapping to Sprite
 #[wasm_bindgen]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ActorId {
     Player1 = 0,
     Player2 = 1,
@@ -22,4 +23,40 @@ pub enum ActorId {
     BombUp = 7,
     BombPowerUp = 8,
     SpeedUp = 9,
+    Floor = 10,
+    Font = 11,
+    /// Palette variants of `Player1` to `Player4` in `sprites.json`
+    Player5 = 12,
+    Player6 = 13,
+    Player7 = 14,
+    Player8 = 15,
+}
+
+/// Characters of players in order of id
+const PLAYERS: [ActorId; 8] = [
+    ActorId::Player1,
+    ActorId::Player2,
+    ActorId::Player3,
+    ActorId::Player4,
+    ActorId::Player5,
+    ActorId::Player6,
+    ActorId::Player7,
+    ActorId::Player8,
+];
+
+impl ActorId {
+    /// Character of player `id` if any
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::actors::ActorId;
+    ///
+    /// assert_eq!(ActorId::player(0), Some(ActorId::Player1));
+    /// assert_eq!(ActorId::player(7), Some(ActorId::Player8));
+    /// assert_eq!(ActorId::player(8), None);
+    /// ```
+    pub fn player(id: u32) -> Option<ActorId> {
+        PLAYERS.get(id as usize).copied()
+    }
 }
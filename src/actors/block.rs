@@ -1,15 +1,21 @@
 use crate::actors::*;
+use crate::atlas::{AnimState, Animator};
+use crate::events::GameEvent;
 use crate::game_state::*;
 use crate::geometry::*;
 use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// Ticks a soft block takes to burn down after catching fire
+const BURN_TICKS: i32 = 28;
 
 /// Block
 
+#[derive(Clone, Debug, Hash, Serialize, Deserialize)]
 pub struct Block {
-    actor_id: ActorId,
-    action: u32,
+    animator: Animator,
     ttl: i32,
-    pub pnt: Point,
+    pub pnt: PixelPos,
 }
 
 impl Block {
@@ -18,47 +24,60 @@ impl Block {
     }
 
     pub fn hard(x: i32, y: i32) -> Self {
-        Block::build(x, y, 30, 0)
+        Block::build(x, y, 30, AnimState::Hard)
     }
 
     pub fn soft(x: i32, y: i32) -> Self {
-        Block::build(x, y, 28, 1)
+        Block::build(x, y, BURN_TICKS, AnimState::Soft)
     }
 
     pub fn is_soft(&self) -> bool {
         self.ttl < 30
     }
 
+    pub fn is_burning(&self) -> bool {
+        self.ttl < BURN_TICKS
+    }
+
     pub fn alive(&self) -> bool {
         self.ttl > 0
     }
 
-    pub fn draw(&self) {
-        screen_put_sprite(self.pnt.x, self.pnt.y, self.actor_id, self.action)
+    /// Layer to be drawn on
+    pub fn layer(&self) -> Layer {
+        Layer::Blocks
+    }
+
+    pub fn draw(&self, list: &mut DrawList) {
+        list.put_animation(self.layer(), self.pnt.x, self.pnt.y, &self.animator)
     }
 
-    pub fn update(&mut self, _delta: i32, gs: &GameState) {
+    pub fn update(&mut self, delta: i32, gs: &GameState) {
         // hardblock →nothing to do.
         if !self.is_soft() {
             return;
         }
         // softblock → check if fired
-        if self.ttl == 28 {
+        if self.ttl == BURN_TICKS {
             let fire_exists = gs.fires().iter().any(|f| f.pnt == self.pnt);
             if fire_exists {
                 self.ttl -= 1;
+                self.animator.set_state(AnimState::Burning);
+                gs.push_event(GameEvent::BlockDestroyed {
+                    x: self.pnt.x,
+                    y: self.pnt.y,
+                });
             }
         } else {
             // burning the block
             self.ttl -= 1;
+            self.animator.advance(delta as u32);
         }
-        self.action = (15 - self.ttl / 2) as u32;
     }
 
-    fn build(x: i32, y: i32, ttl: i32, action: u32) -> Self {
+    fn build(x: i32, y: i32, ttl: i32, state: AnimState) -> Self {
         Block {
-            actor_id: ActorId::Block,
-            action,
+            animator: Animator::new(ActorId::Block, state),
             ttl,
             pnt: grd!(x, y),
         }
@@ -1,21 +1,22 @@
 use crate::actors::*;
+use crate::atlas::{AnimState, Animator};
 use crate::game_state::*;
 use crate::geometry::*;
 use crate::*;
+use serde::{Deserialize, Serialize};
 
 /// Bomb
 
+#[derive(Clone, Debug, Hash, Serialize, Deserialize)]
 pub struct Bomb {
-    /// Id to distinguish each Actor
-    actor_id: ActorId,
-    /// Current status or action of the bomb
-    action: u32,
+    /// Current animation of the bomb
+    animator: Animator,
     /// Time to Live
     ttl: i32,
     /// Id to distinguish who put the bomb
     pub owner_id: u32,
     /// Current location of Bomb
-    pub pnt: Point,
+    pub pnt: PixelPos,
     /// Power of Bomb
     pub power: u8,
 }
@@ -23,8 +24,7 @@ pub struct Bomb {
 impl Bomb {
     pub fn new(owner_id: u32, x: i32, y: i32, power: u8) -> Self {
         Bomb {
-            actor_id: ActorId::Bomb,
-            action: 0,
+            animator: Animator::new(ActorId::Bomb, AnimState::Fuse),
             ttl: 300,
             owner_id,
             pnt: grd!(x, y),
@@ -36,8 +36,13 @@ impl Bomb {
         self.ttl > 0
     }
 
-    pub fn draw(&self) {
-        screen_put_sprite(self.pnt.x, self.pnt.y, self.actor_id, self.action)
+    /// Layer to be drawn on
+    pub fn layer(&self) -> Layer {
+        Layer::Bombs
+    }
+
+    pub fn draw(&self, list: &mut DrawList) {
+        list.put_animation(self.layer(), self.pnt.x, self.pnt.y, &self.animator)
     }
 
     pub fn update(&mut self, delta: i32, gs: &GameState) {
@@ -51,6 +56,6 @@ impl Bomb {
         if fire_exists && self.ttl > 5 {
             self.ttl = 5;
         }
-        self.action = (300 - self.ttl) as u32 * 15 / 300;
+        self.animator.advance(delta as u32);
     }
 }
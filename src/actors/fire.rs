@@ -1,37 +1,116 @@
 use crate::actors::*;
+use crate::atlas::{AnimState, Animator};
 use crate::geometry::*;
 use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// Part of an explosion a fire makes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FireKind {
+    /// Where the bomb was, or where flames cross
+    Center,
+    /// Middle of an arm going east and west
+    Horizontal,
+    /// Middle of an arm going north and south
+    Vertical,
+    /// End of the arm going in the direction
+    Tip(Direction),
+}
+
+impl FireKind {
+    /// Piece of the flames of `self` and `other` at the same place
+    ///
+    /// ```
+    /// use bomberhuman::actors::fire::FireKind::*;
+    /// use bomberhuman::geometry::Direction;
+    ///
+    /// assert_eq!(Tip(Direction::W).merge(Tip(Direction::E)), Horizontal);
+    /// assert_eq!(Horizontal.merge(Tip(Direction::S)), Center);
+    /// ```
+    pub fn merge(self, other: FireKind) -> FireKind {
+        match (self.axis(), other.axis()) {
+            _ if self == other => self,
+            (Some(a), Some(b)) if a == b => a,
+            _ => FireKind::Center,
+        }
+    }
+
+    /// `Horizontal` or `Vertical` if the piece goes only along it
+    fn axis(self) -> Option<FireKind> {
+        match self {
+            FireKind::Horizontal | FireKind::Tip(Direction::W) | FireKind::Tip(Direction::E) => {
+                Some(FireKind::Horizontal)
+            }
+            FireKind::Vertical | FireKind::Tip(Direction::N) | FireKind::Tip(Direction::S) => {
+                Some(FireKind::Vertical)
+            }
+            FireKind::Center => None,
+        }
+    }
+
+    fn anim_state(self) -> AnimState {
+        match self {
+            FireKind::Center => AnimState::Center,
+            FireKind::Horizontal => AnimState::ArmH,
+            FireKind::Vertical => AnimState::ArmV,
+            FireKind::Tip(Direction::N) => AnimState::TipN,
+            FireKind::Tip(Direction::W) => AnimState::TipW,
+            FireKind::Tip(Direction::S) => AnimState::TipS,
+            FireKind::Tip(Direction::E) => AnimState::TipE,
+        }
+    }
+}
 
 /// Fire
 
+#[derive(Clone, Debug, Hash, Serialize, Deserialize)]
 pub struct Fire {
-    actor_id: ActorId,
-    action: u32,
+    animator: Animator,
     ttl: i32,
-    pub pnt: Point,
+    pub pnt: PixelPos,
+    pub kind: FireKind,
+    /// Players whose bombs made the fire, more than one if merged
+    pub owners: Vec<u32>,
 }
 
 impl Fire {
-    pub fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: i32, y: i32, kind: FireKind, owner: u32) -> Self {
         Fire {
-            actor_id: ActorId::Fire,
-            action: 0,
+            animator: Animator::new(ActorId::Fire, kind.anim_state()),
             ttl: 20,
             pnt: pnt!(x, y),
+            kind,
+            owners: vec![owner],
+        }
+    }
+
+    /// Fire of `kind` by `owner` put on this one
+    ///
+    /// The flames start over, and the owners of both are kept.
+    pub fn merge(&self, kind: FireKind, owner: u32) -> Fire {
+        let mut fire = Fire::new(self.pnt.x, self.pnt.y, self.kind.merge(kind), owner);
+        fire.owners = self.owners.clone();
+        if !fire.owners.contains(&owner) {
+            fire.owners.push(owner);
         }
+        fire
     }
 
     pub fn alive(&self) -> bool {
         self.ttl > 0
     }
 
-    pub fn draw(&self) {
-        screen_put_sprite(self.pnt.x, self.pnt.y, self.actor_id, self.action)
+    /// Layer to be drawn on
+    pub fn layer(&self) -> Layer {
+        Layer::Effects
+    }
+
+    pub fn draw(&self, list: &mut DrawList) {
+        list.put_animation(self.layer(), self.pnt.x, self.pnt.y, &self.animator)
     }
 
-    pub fn update(&mut self, _delta: i32) {
-        // XXX: action is 0-5 for the sake of sprites.png should FIX it.
-        self.action = (20 - self.ttl as u32) * 15 / 20;
+    pub fn update(&mut self, delta: i32) {
+        self.animator.advance(delta as u32);
         self.ttl -= 1;
     }
 }
@@ -1,25 +1,75 @@
 use crate::actors::bomb::*;
 use crate::actors::*;
+use crate::atlas::{AnimState, Animator};
 use std::cmp::min;
 
+use crate::error::Error;
+use crate::events::GameEvent;
 use crate::game_state::*;
 use crate::geometry::*;
 use crate::keyboard::*;
+use crate::rules::Mode;
+use crate::stage::BORDER;
 use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// Walking animation time per pixel moved in ms
+///
+/// At speed level 1 of the default `Rules`, a player moves 2 pixels
+/// per tick of 16ms.
+const MS_PER_PIXEL: u32 = 8;
+
+/// Sub-pixel units of a pixel in `Player::carry`
+///
+/// A speed in pixels per second times a time in ms is in these units.
+const SUBPIXELS: i32 = 1000;
+
+/// Max speed level by `ActorId::SpeedUp`
+const MAX_SPEED: u8 = 8;
+
+/// Ticks to wait before coming back with a life left
+const RESPAWN_TICKS: i32 = 60 * 2;
+
+/// Time not to be hurt by fire after coming back in ms
+const INVULNERABLE_MS: i32 = 3000;
+
+/// Period to blink while invulnerable in ms
+const BLINK_MS: i32 = 100;
+
+/// Cells a ghost throws a bomb into the arena from the outer wall
+pub const LOB_CELLS: i32 = 3;
 
 /// Player
 
+#[derive(Clone, Debug, Hash, Serialize, Deserialize)]
 pub struct Player {
     /// Id to distinguish each Actor
     actor_id: ActorId,
     /// Id to distinguish each Player
     pub id: u32,
-    /// Current status or action of the player
-    action: u32,
+    /// Current animation of the player
+    animator: Animator,
     /// Time to Live
+    ///
+    /// Dead players count up from a negative value to come back if
+    /// they have `lives` left, or as a ghost in `Rules::revenge`.
     ttl: i32,
+    /// Riding the outer wall out of the round in `Rules::revenge`
+    ghost: bool,
+    /// Lives left in the round including the current one
+    lives: u32,
+    /// Time left not to be hurt by fire in ms
+    invulnerable: i32,
+    /// Where the player started the round
+    spawn: PixelPos,
     /// Current location of Player
-    pub pnt: Point,
+    pub pnt: PixelPos,
+    /// Distance moved less than a pixel in 1/`SUBPIXELS` px, carried
+    /// over to the next tick while walking
+    ///
+    /// Together with `pnt`, it makes a fixed-point position along the
+    /// way the player goes.
+    carry: i32,
     /// Current bomb power of Player
     bomb_power: u8,
     /// Current max number of bombs
@@ -32,35 +82,96 @@ impl Player {
     /// Constructor of Player
     ///
     /// `x`, `y` are the initial positions of the player.
-    /// `id` is an id number to distinguish each player.
-    pub fn new(id: u32, x: i32, y: i32) -> Self {
-        let actor_id = match id {
-            0 => ActorId::Player1,
-            1 => ActorId::Player2,
-            2 => ActorId::Player3,
-            3 => ActorId::Player4,
-            _ => ActorId::Player1,
-        };
-        Player {
+    /// `id` is an id number to distinguish each player below
+    /// `MAX_PLAYERS`.
+    pub fn new(id: u32, x: i32, y: i32) -> Result<Self, Error> {
+        let actor_id = ActorId::player(id).ok_or(Error::NoSuchPlayer(id))?;
+        Ok(Player {
             id,
             actor_id,
-            action: 1000,
+            animator: Animator::new(actor_id, AnimState::StandS),
             ttl: 1,
+            ghost: false,
+            lives: 1,
+            invulnerable: 0,
+            spawn: grd!(x, y),
             pnt: grd!(x, y),
+            carry: 0,
             bomb_power: 2,
             max_num_bombs: 1,
             speed: 1,
-        }
+        })
+    }
+
+    /// Id to map the player to Sprite
+    pub fn actor_id(&self) -> ActorId {
+        self.actor_id
+    }
+
+    /// Layer to be drawn on
+    pub fn layer(&self) -> Layer {
+        Layer::Characters
     }
 
     /// Draw player on screen
-    pub fn draw(&self) {
-        screen_put_sprite(self.pnt.x, self.pnt.y, self.actor_id, self.action / 1000)
+    pub fn draw(&self, list: &mut DrawList) {
+        if self.visible() {
+            list.put_animation(self.layer(), self.pnt.x, self.pnt.y, &self.animator)
+        }
+    }
+
+    /// Predicate the player is shown, not in the off phase of blinking
+    pub fn visible(&self) -> bool {
+        self.invulnerable <= 0 || (self.invulnerable / BLINK_MS) % 2 == 0
     }
 
-    /// Predicate to check the player is alive
+    /// Predicate fire does not hurt the player after coming back
+    pub fn invulnerable(&self) -> bool {
+        self.invulnerable > 0
+    }
+
+    /// Lives left in the round including the current one
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    /// Start the round with `lives`
+    pub fn set_lives(&mut self, lives: u32) {
+        self.lives = lives;
+    }
+
+    /// Predicate the player has lost all the lives in the round
+    pub fn out(&self) -> bool {
+        self.lives == 0
+    }
+
+    /// Where the player started the round
+    pub fn spawn(&self) -> PixelPos {
+        self.spawn
+    }
+
+    /// Max number of bombs put at once
+    pub fn max_num_bombs(&self) -> u8 {
+        self.max_num_bombs
+    }
+
+    /// Cells reached by the fire of the bombs
+    pub fn bomb_power(&self) -> u8 {
+        self.bomb_power
+    }
+
+    pub fn speed(&self) -> u8 {
+        self.speed
+    }
+
+    /// Predicate to check the player is alive in the arena
     pub fn alive(&self) -> bool {
-        self.ttl > 0
+        self.ttl > 0 && !self.ghost
+    }
+
+    /// Predicate the player rides the outer wall as a ghost
+    pub fn ghost(&self) -> bool {
+        self.ghost
     }
 
     /// Update function for players
@@ -68,111 +179,206 @@ impl Player {
     /// This function is supposed to be called for each frame of the game.
     /// According to the `key_state`, it acts on the GameState (`gs`).
     pub fn update(&mut self, delta: i32, gs: &GameState, key_state: &KeyState) {
-        if !self.alive() {
-            self.ttl += 1;
+        if !self.alive() && !self.ghost {
+            if !self.out() || gs.rules().revenge {
+                self.ttl += 1;
+                if self.ttl > 0 {
+                    if self.out() {
+                        self.haunt(gs);
+                    } else {
+                        self.respawn(gs, self.spawn.to_cell());
+                    }
+                }
+            }
             return;
         }
+        self.invulnerable = (self.invulnerable - delta.max(0)).max(0);
 
-        if self.action == 15 * 1000 {
-            self.action = 1000;
-        }
+        let travel = self.carry + gs.rules().speed(self.speed) as i32 * delta.max(0);
+        // Less than a cell per tick not to skip over fire or items
+        let speed = min(travel / SUBPIXELS, GS - 1);
+        let (ax, ay) = key_state.axis();
+        let (dx, dy) = (ax * speed, ay * speed);
 
-        let speed = delta / 6 * self.speed as i32; // 1frame = 16ms, speed should be 5 or so.
-        let mut dx = 0;
-        let mut dy = 0;
+        if self.ghost {
+            // One bomb for each press, not for each tick held
+            if key_state.pressed(Key::Button1) {
+                self.lob(gs);
+            }
+            let dxy = BORDER.ride(self.pnt, off!(dx, dy));
+            self.pnt += dxy;
+            self.animate(dxy);
+            self.carry = if dxy.is_zero() { 0 } else { travel % SUBPIXELS };
+            return;
+        }
 
         let mut bombs = gs.bombs_mut();
         let blocks = gs.blocks();
 
-        if key_state.left {
-            dx = -speed;
-        }
-        if key_state.right {
-            dx = speed;
-        }
-        if key_state.up {
-            dy = -speed;
-        }
-        if key_state.down {
-            dy = speed;
-        }
-        if key_state.button1 {
-            let sum: i32 = bombs.iter().fold(
-                0,
-                |acc, b| if b.owner_id == self.id { acc + 1 } else { acc },
-            );
-            if sum < self.max_num_bombs as i32 {
-                let pnt = self.pnt.align_to_grid();
-                if !bombs.iter().any(|b| b.pnt == pnt) {
-                    bombs.push(Bomb::new(self.id, pnt.x, pnt.y, self.bomb_power));
-                }
-            }
+        // One bomb for each press, not for each tick held
+        if key_state.pressed(Key::Button1) {
+            self.put_bomb(gs, &mut bombs, self.pnt.align_to_grid());
         }
 
-        let dxy = self.pnt.adjust_vector_to_grid(pnt!(dx, dy));
-        let new_xy = self.pnt + dxy;
+        // Bombs under the player do not block until they leave, and
+        // teammates walk through each other's bombs.
+        let rules = gs.rules();
+        let obstacles: Vec<PixelPos> = blocks
+            .iter()
+            .map(|b| b.pnt)
+            .chain(
+                bombs
+                    .iter()
+                    .filter(|b| !rules.teammates(b.owner_id, self.id))
+                    .map(|b| b.pnt),
+            )
+            .filter(|&o| !self.pnt.collides_with(o))
+            .collect();
 
-        let block_exists = blocks.iter().any(|b| b.pnt.collides_with(new_xy));
+        let blocked = |c: CellPos| obstacles.contains(&c.to_pixel());
+        let v = self.pnt.resolve_diagonal(off!(dx, dy), blocked);
+        let dxy = self
+            .pnt
+            .assist_corner(v, blocked)
+            .unwrap_or_else(|| self.pnt.adjust_offset_to_grid(v));
+        let dxy = self.pnt.clip_to_contact(dxy, &obstacles);
 
-        let bomb_exists = bombs
-            .iter()
-            // bomb exists at new_xy, but does not exist at current position
-            .any(|b| !self.pnt.collides_with(b.pnt) && new_xy.collides_with(b.pnt));
+        self.pnt += dxy;
+        self.animate(dxy);
+        // Stopping, even by a wall, throws away the fraction.
+        self.carry = if dxy.is_zero() { 0 } else { travel % SUBPIXELS };
 
-        if !block_exists && !bomb_exists {
-            self.pnt += dxy;
-            self.action = self.vector_to_action(dxy);
-        } else {
-            // XXX: Making (dx, dy) zero is bad idea. Should make (dx, dy) be shorten
-            // to keep the safe distance, instead.
+        // Killed by the first owner of the fire hurting the player
+        let killer = gs
+            .fires()
+            .iter()
+            .filter(|f| self.pnt.collides_with(f.pnt))
+            .flat_map(|f| f.owners.clone())
+            .find(|&o| rules.hurts(o, self.id));
+        if let Some(killer) = killer.filter(|_| !self.invulnerable()) {
+            self.animator.set_state(AnimState::Dead);
+            if rules.mode != Mode::Deathmatch {
+                self.lives = self.lives.saturating_sub(1);
+            }
+            self.ttl = if self.out() && !rules.revenge {
+                0
+            } else {
+                -RESPAWN_TICKS
+            };
+            gs.record_kill(killer, self);
+            gs.push_event(GameEvent::PlayerDied {
+                player: self.id,
+                killer,
+                x: self.pnt.x,
+                y: self.pnt.y,
+            });
         }
+    }
 
-        let fire_exists = gs.fires().iter().any(|f| self.pnt.collides_with(f.pnt));
-        if fire_exists {
-            self.action = 15 * 1000;
-            self.ttl = -60 * 8;
+    /// Put a bomb at `pnt` unless there is one, or the player has put
+    /// as many as possible.
+    fn put_bomb(&self, gs: &GameState, bombs: &mut Vec<Bomb>, pnt: PixelPos) {
+        let sum = bombs.iter().filter(|b| b.owner_id == self.id).count();
+        if sum < self.max_num_bombs as usize && !bombs.iter().any(|b| b.pnt == pnt) {
+            bombs.push(Bomb::new(self.id, pnt.x, pnt.y, self.bomb_power));
+            gs.push_event(GameEvent::BombPlaced {
+                player: self.id,
+                x: pnt.x,
+                y: pnt.y,
+            });
         }
     }
 
-    /// Select animation pattern from delta and current action
-    ///
-    /// We have 12 action-images in sprites.png
-    /// No 1, 4, 7, 10 is the base action
+    /// Throw a bomb `LOB_CELLS` into the arena from the edge of the
+    /// outer wall, or short of it if the cell is taken
     ///
-    /// 0 front-right-leg
-    /// 1 front-stay
-    /// 2 front-left-leg
-    /// 3 left-right-leg
-    /// 4 left-stay
-    /// 5 left-left-leg
-    /// 6 right-right-leg
-    /// 7 right-stay
-    /// 8 right-left-leg
-    /// 9 back-right-leg
-    /// 10 back-stay
-    /// 11 back-left-leg
-    ///
-    fn vector_to_action(&self, dxy: Vector) -> u32 {
-        if let Some(x) = dxy.cardinal_direction() {
-            let base_index = match x {
-                Direction::S => 0,
-                Direction::W => 3000,
-                Direction::E => 6000,
-                Direction::N => 9000,
+    /// Ghosts cannot throw from the corners.
+    fn lob(&self, gs: &GameState) {
+        let mut cell = self.pnt.to_cell();
+        let dir = match BORDER.inward(cell) {
+            Some(dir) => dir,
+            None => return,
+        };
+        let mut cells = vec![];
+        for _ in 0..LOB_CELLS {
+            cell = cell.step(dir);
+            cells.push(cell);
+        }
+        let mut bombs = gs.bombs_mut();
+        let target = {
+            let blocks = gs.blocks();
+            let taken = |c: CellPos| {
+                let pnt = c.to_pixel();
+                blocks.iter().any(|b| b.pnt == pnt) || bombs.iter().any(|b| b.pnt == pnt)
             };
-            let current_offset = self.action % 3000;
-            base_index + ((current_offset + (dxy.length() * 50) as u32) % 3000)
-        } else {
-            self.action
+            cells.into_iter().rev().find(|&c| !taken(c))
+        };
+        if let Some(target) = target {
+            self.put_bomb(gs, &mut bombs, target.to_pixel());
         }
     }
 
+    /// Ride the outer wall from the nearest cell as a ghost
+    fn haunt(&mut self, gs: &GameState) {
+        self.ghost = true;
+        self.pnt = BORDER.nearest(self.pnt.to_cell()).to_pixel();
+        self.carry = 0;
+        self.animator.set_state(AnimState::StandS);
+        gs.push_event(GameEvent::BecameGhost {
+            player: self.id,
+            x: self.pnt.x,
+            y: self.pnt.y,
+        });
+    }
+
+    /// Leave the outer wall for the arena with a life after the ghost
+    /// killed a rival at `pnt`
+    pub fn swap_in(&mut self, gs: &GameState, pnt: PixelPos) {
+        self.ghost = false;
+        self.lives = 1;
+        self.respawn(gs, pnt.to_cell());
+    }
+
+    /// Come back at `cell`, or the nearest safe cell if it is in
+    /// danger, and blink for a while not to be hurt.
+    fn respawn(&mut self, gs: &GameState, cell: CellPos) {
+        self.pnt = gs.safe_cell_near(cell).to_pixel();
+        self.carry = 0;
+        self.invulnerable = INVULNERABLE_MS;
+        self.animator.set_state(AnimState::StandS);
+        gs.push_event(GameEvent::PlayerRespawned {
+            player: self.id,
+            x: self.pnt.x,
+            y: self.pnt.y,
+        });
+    }
+
+    /// Select the animation from the movement `dxy`
+    ///
+    /// The walking animation goes on as the player moves, so that the
+    /// legs keep pace with the speed.
+    fn animate(&mut self, dxy: Offset) {
+        let state = match (dxy.cardinal_direction(), self.animator.state()) {
+            (Some(Direction::S), _) => AnimState::WalkS,
+            (Some(Direction::W), _) => AnimState::WalkW,
+            (Some(Direction::E), _) => AnimState::WalkE,
+            (Some(Direction::N), _) => AnimState::WalkN,
+            (None, AnimState::WalkW) => AnimState::StandW,
+            (None, AnimState::WalkE) => AnimState::StandE,
+            (None, AnimState::WalkN) => AnimState::StandN,
+            (None, AnimState::WalkS) => AnimState::StandS,
+            (None, state) => state,
+        };
+        self.animator.set_state(state);
+        self.animator.advance(dxy.length() as u32 * MS_PER_PIXEL);
+    }
+
     /// bomb, bombpower, speed
     pub fn push_item(&mut self, item_type: ActorId) {
         match item_type {
             ActorId::BombUp => self.max_num_bombs = min(self.max_num_bombs + 1, 8),
             ActorId::BombPowerUp => self.bomb_power = min(self.bomb_power + 1, 8),
-            ActorId::SpeedUp => self.speed = min(self.speed + 1, 30),
+            ActorId::SpeedUp => self.speed = min(self.speed + 1, MAX_SPEED),
             _ => (),
         }
     }
@@ -1,53 +1,74 @@
 use crate::actors::*;
+use crate::atlas::{AnimState, Animator};
+use crate::events::GameEvent;
 use crate::game_state::*;
 use crate::geometry::*;
 use crate::*;
 use rand::seq::SliceRandom;
-// use rand::thread_rng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 /// Power
 
+#[derive(Clone, Debug, Hash, Serialize, Deserialize)]
 pub struct Power {
     /// Id to distinguish each Actor
     actor_id: ActorId,
-    /// Current status or action of the bomb
-    action: u32,
+    /// Current animation of the item
+    animator: Animator,
     /// Time to Live
     ttl: i32,
     /// Current location of Power
-    pub pnt: Point,
+    pub pnt: PixelPos,
 }
 
 impl Power {
     pub fn new(x: i32, y: i32, actor_id: ActorId) -> Self {
         Power {
             actor_id,
-            action: 0,
+            animator: Animator::new(actor_id, AnimState::Idle),
             ttl: 15,
             pnt: grd!(x, y),
         }
     }
 
     /// Create Powerup Item Randomly
-    pub fn random_item(x: i32, y: i32) -> Self {
+    pub fn random_item<R: Rng>(x: i32, y: i32, rng: &mut R) -> Self {
         let items = [ActorId::BombUp, ActorId::BombPowerUp, ActorId::SpeedUp];
-        let actor_id = *items.choose(&mut rand::thread_rng()).unwrap();
+        let actor_id = *items.choose(rng).unwrap();
         Power::new(x, y, actor_id)
     }
 
+    /// Kind of the item
+    pub fn actor_id(&self) -> ActorId {
+        self.actor_id
+    }
+
     pub fn alive(&self) -> bool {
         self.ttl > 0
     }
 
-    pub fn draw(&self) {
-        screen_put_sprite(self.pnt.x, self.pnt.y, self.actor_id, self.action)
+    /// Layer to be drawn on
+    pub fn layer(&self) -> Layer {
+        Layer::Items
+    }
+
+    pub fn draw(&self, list: &mut DrawList) {
+        list.put_animation(self.layer(), self.pnt.x, self.pnt.y, &self.animator)
     }
 
     pub fn update(&mut self, _delta: i32, gs: &GameState) {
         for p in &mut *gs.players_mut() {
-            if p.pnt.align_to_grid() == self.pnt {
+            // Dead players waiting to come back cannot pick it up.
+            if p.alive() && p.pnt.align_to_grid() == self.pnt {
                 p.push_item(self.actor_id);
                 self.ttl = 0;
+                gs.push_event(GameEvent::ItemPicked {
+                    player: p.id,
+                    item: self.actor_id,
+                    x: self.pnt.x,
+                    y: self.pnt.y,
+                });
             }
         }
 
@@ -56,6 +77,5 @@ impl Power {
         if fire_exists && !block_exists {
             self.ttl = 0;
         }
-        self.action = (15 - self.ttl) as u32;
     }
 }
@@ -0,0 +1,236 @@
+//! Sprite atlas and animations
+//!
+//! The layout of `sprites.png` is described in `sprites.json` rather
+//! than in code.  For each actor, it gives the row of the sprite sheet
+//! and the animations of its states:
+//!
+//! ```json
+//! {
+//!   "Bomb": {
+//!     "row": 4,
+//!     "animations": {
+//!       "fuse": { "frames": [0, 14], "duration": 320 }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! `frames` is the inclusive range of columns, and `duration` is the
+//! length of each frame in ms.  Animations stop at the last frame
+//! unless `"loop": true`.  An animation may have its own `row`.
+//!
+//! An actor may have `"palette": n` to be drawn with the colors
+//! changed by the n-th palette of the front end, e.g. `Player5` in the
+//! sprites of `Player1`.
+//!
+//! `Font` has `glyphs` instead of animations: the characters in the
+//! columns of its row and the following rows.  Each glyph is at the
+//! top-left of its cell.
+//!
+//! Row 6 of `sprites.png` has the old fire drawn in one piece, which no
+//! actor uses since explosions are drawn in pieces from row 10.
+//!
+//! Actors hold an `Animator` telling the state and the time in it, and
+//! the frame is looked up when drawn.  So the layout can be changed
+//! without touching the code.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::actors::ActorId;
+use crate::error::Error;
+
+/// Descriptor of `sprites.png` used unless another one is loaded
+pub const DEFAULT_ATLAS: &str = include_str!("../www/assets/sprites.json");
+
+/// State of an actor having its own animation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimState {
+    // Player
+    StandS,
+    StandW,
+    StandE,
+    StandN,
+    WalkS,
+    WalkW,
+    WalkE,
+    WalkN,
+    Dead,
+    // Bomb
+    Fuse,
+    // Block
+    Hard,
+    Soft,
+    Burning,
+    // Fire
+    Center,
+    ArmH,
+    ArmV,
+    TipN,
+    TipW,
+    TipS,
+    TipE,
+    // Power and Floor
+    Idle,
+}
+
+/// Frames of an animation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Animation {
+    /// Row in the sprite sheet if not the one of the actor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<u32>,
+    /// First and last columns in the sprite sheet
+    pub frames: (u32, u32),
+    /// Length of each frame in ms
+    #[serde(default = "default_duration")]
+    pub duration: u32,
+    /// Start over after the last frame
+    #[serde(default, rename = "loop")]
+    pub looping: bool,
+}
+
+fn default_duration() -> u32 {
+    100
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl Animation {
+    /// Column of the frame at `time` ms since the animation started
+    pub fn frame_at(&self, time: u32) -> u32 {
+        let (first, last) = self.frames;
+        let count = last - first + 1;
+        let n = time / self.duration.max(1);
+        if self.looping {
+            first + n % count
+        } else {
+            first + n.min(count - 1)
+        }
+    }
+}
+
+/// Sprites of an actor
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActorSprites {
+    /// Row in the sprite sheet
+    pub row: u32,
+    /// Palette to change the colors, or 0 for the original ones
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub palette: u32,
+    #[serde(default)]
+    pub animations: HashMap<AnimState, Animation>,
+    /// Characters in the columns of each row from `row`, only for `Font`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub glyphs: Vec<String>,
+}
+
+/// Layout of the sprite sheet
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Atlas {
+    actors: HashMap<ActorId, ActorSprites>,
+}
+
+impl Atlas {
+    /// Parse the descriptor described in the module document
+    pub fn from_json(json: &str) -> Result<Atlas, Error> {
+        let atlas: Atlas =
+            serde_json::from_str(json).map_err(|e| Error::InvalidAtlas(e.to_string()))?;
+        for (actor, sprites) in &atlas.actors {
+            for (state, anim) in &sprites.animations {
+                if anim.frames.0 > anim.frames.1 {
+                    return Err(Error::InvalidAtlas(format!(
+                        "empty frames in {:?} {:?}",
+                        actor, state
+                    )));
+                }
+            }
+        }
+        Ok(atlas)
+    }
+
+    /// Row and column of the sprite to draw `animator` if any
+    pub fn frame(&self, animator: &Animator) -> Option<(u32, u32)> {
+        let sprites = self.actors.get(&animator.actor)?;
+        let anim = sprites.animations.get(&animator.state)?;
+        let row = anim.row.unwrap_or(sprites.row);
+        Some((row, anim.frame_at(animator.time)))
+    }
+
+    /// Palette of `actor`, or 0 for the original colors
+    pub fn palette(&self, actor: ActorId) -> u32 {
+        self.actors.get(&actor).map_or(0, |s| s.palette)
+    }
+
+    /// Row and column of the glyph of `c` in the font if any
+    pub fn glyph(&self, c: char) -> Option<(u32, u32)> {
+        let font = self.actors.get(&ActorId::Font)?;
+        font.glyphs.iter().enumerate().find_map(|(i, line)| {
+            let col = line.chars().position(|g| g == c)?;
+            Some((font.row + i as u32, col as u32))
+        })
+    }
+}
+
+impl Default for Atlas {
+    fn default() -> Self {
+        Atlas::from_json(DEFAULT_ATLAS).expect("default atlas should be valid")
+    }
+}
+
+/// Current animation of an actor
+///
+/// # Examples
+///
+/// ```
+/// use bomberhuman::actors::ActorId;
+/// use bomberhuman::atlas::{AnimState, Animator, Atlas};
+///
+/// let atlas = Atlas::default();
+/// let mut bomb = Animator::new(ActorId::Bomb, AnimState::Fuse);
+/// assert_eq!(atlas.frame(&bomb), Some((4, 0)));
+/// bomb.advance(1000);
+/// assert_eq!(atlas.frame(&bomb), Some((4, 3)));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Animator {
+    actor: ActorId,
+    state: AnimState,
+    /// Time since the state started in ms
+    time: u32,
+}
+
+impl Animator {
+    pub fn new(actor: ActorId, state: AnimState) -> Self {
+        Animator {
+            actor,
+            state,
+            time: 0,
+        }
+    }
+
+    pub fn actor(&self) -> ActorId {
+        self.actor
+    }
+
+    pub fn state(&self) -> AnimState {
+        self.state
+    }
+
+    /// Change the state.  The animation starts over if it differs.
+    pub fn set_state(&mut self, state: AnimState) {
+        if self.state != state {
+            self.state = state;
+            self.time = 0;
+        }
+    }
+
+    /// Let `ms` pass in the animation
+    pub fn advance(&mut self, ms: u32) {
+        self.time = self.time.saturating_add(ms);
+    }
+}
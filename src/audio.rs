@@ -0,0 +1,95 @@
+//! Sound effects and background music with WebAudio
+//!
+//! Sounds are synthesized by `synth` on the fly.  The front end passes
+//! the events drained from `GameState` to `AudioEngine::handle_events`,
+//! and calls `AudioEngine::update` once per frame to keep the music
+//! going.
+//!
+//! Browsers refuse to start audio before the user interacts with the
+//! page, so create `AudioEngine` in a key or gamepad handler.
+
+pub mod synth;
+
+use wasm_bindgen::prelude::*;
+use web_sys::AudioContext;
+
+use crate::events::GameEvent;
+use synth::{Music, Sound};
+
+/// Music is scheduled this far ahead in seconds
+const LOOKAHEAD: f64 = 0.2;
+
+/// Length of a chunk of the music in seconds
+const CHUNK: f64 = 0.05;
+
+/// Player of the sounds
+#[wasm_bindgen]
+pub struct AudioEngine {
+    ctx: AudioContext,
+    music: Music,
+    /// The music is scheduled up to this time of `ctx`
+    music_until: f64,
+}
+
+#[wasm_bindgen]
+impl AudioEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<AudioEngine, JsValue> {
+        Ok(AudioEngine {
+            ctx: AudioContext::new()?,
+            music: Music::new(),
+            music_until: 0.0,
+        })
+    }
+
+    /// Play the sounds for `events` taken by `GameState::drain_events`
+    pub fn handle_events(&self, events: JsValue) -> Result<(), JsValue> {
+        let events: Vec<GameEvent> = serde_wasm_bindgen::from_value(events)?;
+        for event in &events {
+            if let Some(sound) = Sound::from_event(event) {
+                self.play(sound)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Schedule the music to follow `time_left` in the round in ms
+    /// given by `GameState::time_left` or `App::time_left`
+    pub fn update(&mut self, time_left: u32) -> Result<(), JsValue> {
+        let now = self.ctx.current_time();
+        if self.music_until < now {
+            self.music_until = now;
+        }
+        let bpm = synth::tempo(time_left);
+        while self.music_until < now + LOOKAHEAD {
+            let sample_rate = self.ctx.sample_rate();
+            let mut samples = vec![0.0; (CHUNK * sample_rate as f64) as usize];
+            self.music.render(&mut samples, sample_rate as u32, bpm);
+            self.schedule(&samples, self.music_until)?;
+            self.music_until += CHUNK;
+        }
+        Ok(())
+    }
+}
+
+impl AudioEngine {
+    /// Play `sound` right now
+    pub fn play(&self, sound: Sound) -> Result<(), JsValue> {
+        let samples = sound.render(self.ctx.sample_rate() as u32);
+        self.schedule(&samples, 0.0)
+    }
+
+    /// Play `samples` at `when` in the time of the context
+    fn schedule(&self, samples: &[f32], when: f64) -> Result<(), JsValue> {
+        let sample_rate = self.ctx.sample_rate();
+        let buffer = self
+            .ctx
+            .create_buffer(1, samples.len() as u32, sample_rate)?;
+        buffer.copy_to_channel(samples, 0)?;
+        let source = self.ctx.create_buffer_source()?;
+        source.set_buffer(Some(&buffer));
+        source.connect_with_audio_node(&self.ctx.destination())?;
+        source.start_with_when(when)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+//! Procedural sound synthesis
+//!
+//! Every sound is rendered from oscillators and noise into a buffer of
+//! samples in `-1.0..=1.0`, so that no asset files are needed and the
+//! output can be checked natively.
+
+use std::f32::consts::PI;
+
+use crate::events::GameEvent;
+
+/// Tempo of the background music in beats per minute
+pub const BASE_BPM: f32 = 132.0;
+
+/// Tempo at the end of a round
+pub const MAX_BPM: f32 = 198.0;
+
+/// The music speeds up when the time left becomes shorter than this
+pub const HURRY_MS: u32 = 30 * 1000;
+
+/// Sound effects
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sound {
+    /// Explosion of a bomb with the power
+    Explosion(u8),
+    /// A bomb is placed and its fuse starts to burn
+    FuseTick,
+    /// An item is picked up
+    Pickup,
+    /// A player is killed
+    Death,
+}
+
+impl Sound {
+    /// Sound to be played on `event` if any
+    pub fn from_event(event: &GameEvent) -> Option<Sound> {
+        match *event {
+            GameEvent::BombPlaced { .. } => Some(Sound::FuseTick),
+            GameEvent::Exploded { power, .. } => Some(Sound::Explosion(power)),
+            GameEvent::ItemPicked { .. } => Some(Sound::Pickup),
+            GameEvent::PlayerDied { .. } => Some(Sound::Death),
+            _ => None,
+        }
+    }
+
+    /// Length in seconds
+    pub fn duration(&self) -> f32 {
+        match *self {
+            Sound::Explosion(power) => 0.4 + 0.05 * power.min(8) as f32,
+            Sound::FuseTick => 0.06,
+            Sound::Pickup => 0.16,
+            Sound::Death => 0.7,
+        }
+    }
+
+    /// Render the whole sound at `sample_rate`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::audio::synth::Sound;
+    ///
+    /// let samples = Sound::Pickup.render(8000);
+    /// assert_eq!(samples.len(), 1280);
+    /// assert!(samples.iter().all(|s| s.abs() <= 1.0));
+    /// ```
+    pub fn render(&self, sample_rate: u32) -> Vec<f32> {
+        let len = (self.duration() * sample_rate as f32) as usize;
+        let dt = 1.0 / sample_rate as f32;
+        let mut noise = Noise::new(0x2545_f491);
+        let mut lowpass = 0.0;
+        let mut phase = 0.0;
+
+        (0..len)
+            .map(|i| {
+                let t = i as f32 * dt;
+                let progress = i as f32 / len as f32;
+                let sample = match *self {
+                    Sound::Explosion(_) => {
+                        // Rumbling noise over a thump sweeping down
+                        lowpass += (noise.next() - lowpass) * 0.08;
+                        phase += (90.0 - 50.0 * progress) * dt;
+                        let env = (1.0 - progress).powi(2);
+                        (2.5 * lowpass + 0.6 * sine(phase)) * env
+                    }
+                    Sound::FuseTick => {
+                        // Crackle: differentiated noise, a high-pass filter
+                        let n = noise.next();
+                        let s = n - lowpass;
+                        lowpass = n;
+                        0.4 * s * (-t * 60.0).exp()
+                    }
+                    Sound::Pickup => {
+                        // Two notes going up
+                        let freq = if progress < 0.4 { 660.0 } else { 990.0 };
+                        phase += freq * dt;
+                        0.3 * square(phase) * (1.0 - progress)
+                    }
+                    Sound::Death => {
+                        // Wobbling sweep down
+                        let freq = 800.0 * (1.0 - 0.85 * progress);
+                        phase += freq * (1.0 + 0.05 * sine(t * 12.0)) * dt;
+                        0.4 * triangle(phase) * (1.0 - progress)
+                    }
+                };
+                sample.clamp(-1.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+/// Tempo of the background music with `time_left` ms in a round
+///
+/// Rises linearly from `BASE_BPM` to `MAX_BPM` in the last `HURRY_MS`.
+pub fn tempo(time_left: u32) -> f32 {
+    if time_left >= HURRY_MS {
+        return BASE_BPM;
+    }
+    let hurry = 1.0 - time_left as f32 / HURRY_MS as f32;
+    BASE_BPM + (MAX_BPM - BASE_BPM) * hurry
+}
+
+/// Melody in MIDI note numbers for each 16th note; 0 is a rest.
+const MELODY: [u8; 32] = [
+    72, 0, 72, 74, 76, 0, 72, 0, 79, 0, 76, 0, 74, 72, 74, 0, //
+    72, 0, 72, 74, 76, 0, 79, 0, 81, 79, 76, 74, 72, 0, 0, 0,
+];
+
+/// Bass in MIDI note numbers for each 8th note
+const BASS: [u8; 16] = [
+    48, 48, 55, 55, 53, 53, 55, 55, 48, 48, 55, 55, 53, 55, 48, 48,
+];
+
+/// Background music looping forever
+///
+/// Rendered in chunks, so that the tempo can change on the way.
+#[derive(Clone, Debug, Default)]
+pub struct Music {
+    /// 16th notes played
+    steps: usize,
+    /// Time in the current step in seconds
+    time_in_step: f32,
+    melody_phase: f32,
+    bass_phase: f32,
+}
+
+impl Music {
+    pub fn new() -> Self {
+        Music::default()
+    }
+
+    /// Number of 16th notes played so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Render the continuation of the music into `out` at `bpm`
+    pub fn render(&mut self, out: &mut [f32], sample_rate: u32, bpm: f32) {
+        let dt = 1.0 / sample_rate as f32;
+        let step_len = 60.0 / bpm / 4.0;
+
+        for sample in out.iter_mut() {
+            let melody = MELODY[self.steps % MELODY.len()];
+            let bass = BASS[self.steps / 2 % BASS.len()];
+            let decay = (1.0 - self.time_in_step / step_len).max(0.0);
+
+            let mut s = 0.0;
+            if melody != 0 {
+                self.melody_phase += note_to_freq(melody) * dt;
+                s += 0.12 * square(self.melody_phase) * decay * decay;
+            }
+            self.bass_phase += note_to_freq(bass) * dt;
+            s += 0.18 * triangle(self.bass_phase);
+            *sample = s;
+
+            self.time_in_step += dt;
+            if self.time_in_step >= step_len {
+                self.time_in_step -= step_len;
+                self.steps += 1;
+            }
+        }
+        // Keep phases small to preserve precision
+        self.melody_phase = self.melody_phase.fract();
+        self.bass_phase = self.bass_phase.fract();
+    }
+}
+
+/// Cheap deterministic white noise (xorshift)
+struct Noise(u32);
+
+impl Noise {
+    fn new(seed: u32) -> Self {
+        Noise(seed)
+    }
+
+    fn next(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+fn note_to_freq(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+// Oscillators taking the phase in cycles
+
+fn sine(phase: f32) -> f32 {
+    (2.0 * PI * phase).sin()
+}
+
+fn square(phase: f32) -> f32 {
+    if phase.fract() < 0.5 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn triangle(phase: f32) -> f32 {
+    4.0 * (phase.fract() - 0.5).abs() - 1.0
+}
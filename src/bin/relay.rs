@@ -0,0 +1,208 @@
+//! Relay server for the online lockstep mode
+//!
+//! Usage: relay [--port 9001] [--players 2] [--input-delay 3]
+//!
+//! Clients join in order as player 1, 2, ...  When all the players
+//! have joined, the relay sends `Message::Start` to each of them, and
+//! then forwards every `Message::Input` to the other players.  When a
+//! client disconnects, `Message::Leave` is sent to the rest.
+//!
+//! To play with two browser tabs on one machine, open
+//! `http://localhost:9000/?relay=ws://localhost:9001` twice.
+
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::{Message as WsMessage, WebSocket};
+
+use bomberhuman::netplay::Message;
+use bomberhuman::rules::MAX_PLAYERS;
+
+/// Interval to check messages to be sent while waiting for a client
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+struct Options {
+    port: u16,
+    players: u32,
+    input_delay: u32,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut opts = Options {
+        port: 9001,
+        players: 2,
+        input_delay: 3,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = |_| format!("invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--port" => opts.port = value.parse().map_err(invalid)?,
+            "--players" => opts.players = value.parse().map_err(invalid)?,
+            "--input-delay" => opts.input_delay = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    if !(1..=MAX_PLAYERS).contains(&opts.players) {
+        return Err(format!("--players should be 1 to {}", MAX_PLAYERS));
+    }
+    Ok(opts)
+}
+
+/// Players in the current match
+struct Hub {
+    input_delay: u32,
+    clients: Vec<Option<Sender<Vec<u8>>>>,
+    started: bool,
+}
+
+impl Hub {
+    fn new(players: u32, input_delay: u32) -> Self {
+        Hub {
+            input_delay,
+            clients: vec![None; players as usize],
+            started: false,
+        }
+    }
+
+    /// Assign a vacant player to the client, and start the match if
+    /// everyone is here.
+    fn join(&mut self, tx: Sender<Vec<u8>>) -> Option<u32> {
+        if self.started {
+            return None;
+        }
+        let player = self.clients.iter().position(|c| c.is_none())?;
+        self.clients[player] = Some(tx);
+
+        if self.clients.iter().all(|c| c.is_some()) {
+            let seed = rand::random();
+            for (i, tx) in self.clients.iter().enumerate() {
+                let start = Message::Start {
+                    player: i as u32,
+                    players: self.clients.len() as u32,
+                    seed,
+                    input_delay: self.input_delay,
+                };
+                if let Some(tx) = tx {
+                    let _ = tx.send(start.encode());
+                }
+            }
+            self.started = true;
+            println!("match started");
+        }
+        Some(player as u32)
+    }
+
+    fn leave(&mut self, player: u32) {
+        self.clients[player as usize] = None;
+        if self.started {
+            self.broadcast(player, &Message::Leave { player });
+        }
+        if self.clients.iter().all(|c| c.is_none()) {
+            self.started = false;
+            println!("match finished");
+        }
+    }
+
+    /// Send `msg` to all the players except `from`
+    fn broadcast(&self, from: u32, msg: &Message) {
+        let data = msg.encode();
+        for (i, tx) in self.clients.iter().enumerate() {
+            if let Some(tx) = tx {
+                if i as u32 != from {
+                    let _ = tx.send(data.clone());
+                }
+            }
+        }
+    }
+}
+
+fn serve(stream: TcpStream, hub: Arc<Mutex<Hub>>) -> Result<(), String> {
+    let mut ws = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+    ws.get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+
+    let (tx, rx) = mpsc::channel();
+    let player = match hub.lock().unwrap().join(tx) {
+        Some(player) => player,
+        None => {
+            let _ = ws.close(None);
+            return Err("match is full".to_string());
+        }
+    };
+    println!("player {} joined", player + 1);
+
+    let result = relay_messages(&mut ws, &rx, player, &hub);
+    hub.lock().unwrap().leave(player);
+    println!("player {} left", player + 1);
+    result
+}
+
+/// Forward inputs from the client to the hub, and messages from the
+/// hub to the client until the connection is closed.
+fn relay_messages(
+    ws: &mut WebSocket<TcpStream>,
+    rx: &Receiver<Vec<u8>>,
+    player: u32,
+    hub: &Mutex<Hub>,
+) -> Result<(), String> {
+    loop {
+        match ws.read() {
+            Ok(WsMessage::Binary(data)) => {
+                // Trust the connection rather than the player in the message.
+                if let Ok(Message::Input { tick, keys, .. }) = Message::decode(&data) {
+                    let input = Message::Input { tick, player, keys };
+                    hub.lock().unwrap().broadcast(player, &input);
+                }
+            }
+            Ok(WsMessage::Close(_)) => return Ok(()),
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(e)) if is_timeout(&e) => (),
+            Err(e) => return Err(e.to_string()),
+        }
+        while let Ok(data) = rx.try_recv() {
+            ws.send(WsMessage::Binary(data))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut
+}
+
+fn main() -> io::Result<()> {
+    let opts = match parse_options() {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("relay: {}", msg);
+            eprintln!("usage: relay [--port 9001] [--players 2] [--input-delay 3]");
+            std::process::exit(1);
+        }
+    };
+    let listener = TcpListener::bind(("127.0.0.1", opts.port))?;
+    let hub = Arc::new(Mutex::new(Hub::new(opts.players, opts.input_delay)));
+    println!(
+        "relay listening on ws://localhost:{} for {} players",
+        opts.port, opts.players
+    );
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let hub = Arc::clone(&hub);
+        thread::spawn(move || {
+            if let Err(msg) = serve(stream, hub) {
+                eprintln!("relay: {}", msg);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//! Authoritative game server with spectator streaming
+//!
+//! Usage: server [--port 9002] [--players 4]
+//!
+//! The server owns the only `GameState`, and clients just send their
+//! key inputs.  Clients connect with WebSocket; they become players in
+//! order while a player is vacant, and the rest (or those connecting
+//! to `/spectate`) watch the game.  Each client receives the whole
+//! state once, and then the compact delta for each tick, so that
+//! spectators can join in the middle of a match.
+//!
+//! Open `http://localhost:9000/?server=ws://localhost:9002` in browser
+//! tabs, or `?server=ws://localhost:9002/spectate` to watch.  The
+//! terminal version also works with `tui --server ws://localhost:9002`.
+//!
+//! Rounds go on until a player wins the match, and then the server
+//! quits.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::{Message as WsMessage, WebSocket};
+
+use bomberhuman::delta;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::{GameState, RoundEnd, TICK_MS};
+use bomberhuman::keyboard::KeyState;
+use bomberhuman::netplay::Message;
+use bomberhuman::rules::{Rules, MAX_PLAYERS, ROUND_END_MS};
+
+/// Interval to check messages to be sent while waiting for a client
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+struct Options {
+    port: u16,
+    players: u32,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut opts = Options {
+        port: 9002,
+        players: 4,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = |_| format!("invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--port" => opts.port = value.parse().map_err(invalid)?,
+            "--players" => opts.players = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    if !(1..=MAX_PLAYERS).contains(&opts.players) {
+        return Err(format!("--players should be 1 to {}", MAX_PLAYERS));
+    }
+    Ok(opts)
+}
+
+struct Client {
+    tx: Sender<Vec<u8>>,
+    /// The whole state has been sent
+    synced: bool,
+}
+
+/// Clients and their inputs
+struct Hub {
+    clients: HashMap<u64, Client>,
+    next_id: u64,
+    /// Client controlling each player
+    seats: Vec<Option<u64>>,
+    /// Keys of each player
+    inputs: Vec<KeyState>,
+}
+
+impl Hub {
+    fn new(players: u32) -> Self {
+        Hub {
+            clients: HashMap::new(),
+            next_id: 0,
+            seats: vec![None; players as usize],
+            inputs: vec![KeyState::new(); players as usize],
+        }
+    }
+
+    /// Register a client, and seat it on a vacant player unless it
+    /// wants to spectate.
+    fn join(&mut self, tx: Sender<Vec<u8>>, spectate: bool) -> (u64, Option<u32>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.clients.insert(id, Client { tx, synced: false });
+
+        let seat = self.seats.iter().position(|s| s.is_none());
+        match seat {
+            Some(player) if !spectate => {
+                self.seats[player] = Some(id);
+                (id, Some(player as u32))
+            }
+            _ => (id, None),
+        }
+    }
+
+    fn leave(&mut self, id: u64, player: Option<u32>) {
+        self.clients.remove(&id);
+        if let Some(p) = player {
+            self.seats[p as usize] = None;
+            self.inputs[p as usize] = KeyState::new();
+        }
+    }
+
+    /// Send the state at `tick`: the whole for new clients, and the
+    /// delta from `old` for the others.
+    fn broadcast(&mut self, tick: u32, old: &[u8], new: &[u8]) {
+        let mut delta_msg = None;
+        let mut state_msg = None;
+        for client in self.clients.values_mut() {
+            let msg = if client.synced {
+                delta_msg.get_or_insert_with(|| {
+                    let data = delta::encode(old, new);
+                    Message::Delta { tick, data }.encode()
+                })
+            } else {
+                client.synced = true;
+                state_msg.get_or_insert_with(|| {
+                    let data = new.to_vec();
+                    Message::State { tick, data }.encode()
+                })
+            };
+            let _ = client.tx.send(msg.clone());
+        }
+    }
+}
+
+// The callback of `accept_hdr` has to return the large error type of tungstenite.
+#[allow(clippy::result_large_err)]
+fn serve(stream: TcpStream, hub: Arc<Mutex<Hub>>) -> Result<(), String> {
+    let mut path = String::new();
+    let mut ws = tungstenite::accept_hdr(stream, |req: &Request, resp: Response| {
+        path = req.uri().path().to_string();
+        Ok(resp)
+    })
+    .map_err(|e| e.to_string())?;
+    ws.get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+
+    let (tx, rx) = mpsc::channel();
+    let (id, player) = hub.lock().unwrap().join(tx, path == "/spectate");
+    match player {
+        Some(p) => println!("player {} joined", p + 1),
+        None => println!("spectator joined"),
+    }
+
+    let result = relay_messages(&mut ws, &rx, player, &hub);
+
+    hub.lock().unwrap().leave(id, player);
+    match player {
+        Some(p) => println!("player {} left", p + 1),
+        None => println!("spectator left"),
+    }
+    result
+}
+
+/// Take inputs from the client, and send states from the hub to the
+/// client until the connection is closed.
+fn relay_messages(
+    ws: &mut WebSocket<TcpStream>,
+    rx: &Receiver<Vec<u8>>,
+    player: Option<u32>,
+    hub: &Mutex<Hub>,
+) -> Result<(), String> {
+    loop {
+        match ws.read() {
+            Ok(WsMessage::Binary(data)) => {
+                if let (Some(p), Ok(Message::Input { keys, .. })) = (player, Message::decode(&data))
+                {
+                    hub.lock().unwrap().inputs[p as usize] = KeyState::from_bits(keys);
+                }
+            }
+            Ok(WsMessage::Close(_)) => return Ok(()),
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(e)) if is_timeout(&e) => (),
+            Err(e) => return Err(e.to_string()),
+        }
+        while let Ok(data) = rx.try_recv() {
+            ws.send(WsMessage::Binary(data))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut
+}
+
+fn main() -> io::Result<()> {
+    let opts = match parse_options() {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("server: {}", msg);
+            eprintln!("usage: server [--port 9002] [--players 4]");
+            std::process::exit(1);
+        }
+    };
+    let listener = TcpListener::bind(("127.0.0.1", opts.port))?;
+    let hub = Arc::new(Mutex::new(Hub::new(opts.players)));
+    println!(
+        "server listening on ws://localhost:{} for {} players",
+        opts.port, opts.players
+    );
+
+    let accepting = Arc::clone(&hub);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let hub = Arc::clone(&accepting);
+            thread::spawn(move || {
+                if let Err(msg) = serve(stream, hub) {
+                    eprintln!("server: {}", msg);
+                }
+            });
+        }
+    });
+
+    let tick = Duration::from_millis(TICK_MS as u64);
+    let rules = Rules {
+        players: opts.players,
+        ..Rules::default()
+    };
+    let mut gs = GameState::with_rules(900, 780, rand::random(), rules);
+    let mut last = gs.snapshot();
+    // Time left to show the end of the round
+    let mut round_end = None;
+    loop {
+        let start = Instant::now();
+        {
+            let mut hub = hub.lock().unwrap();
+            gs.step_with(&hub.inputs);
+            for event in gs.take_events() {
+                if let GameEvent::RoundOver { winner } = event {
+                    match winner {
+                        Some(p) => println!("player {} won the round", p + 1),
+                        None => println!("draw"),
+                    }
+                    round_end = Some(ROUND_END_MS);
+                }
+            }
+            if let Some(wait) = round_end {
+                match gs.round_end_step(wait, TICK_MS) {
+                    RoundEnd::Showing(wait) => round_end = Some(wait),
+                    RoundEnd::NextRound => round_end = None,
+                    RoundEnd::MatchOver(p) => {
+                        println!("player {} won the match", p + 1);
+                        return Ok(());
+                    }
+                }
+            }
+            let state = gs.snapshot();
+            hub.broadcast(gs.tick(), &last, &state);
+            last = state;
+        }
+        if let Some(rest) = tick.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Terminal front end for playing and spectating over SSH
+//!
+//! Usage: tui [--spectate] [--server ws://localhost:9002] [--keys keys.json]
+//!
+//! Keys are the same as the browser version. Esc or Ctrl-C to quit.
+//! `--keys` loads the bindings saved by `InputMap::to_json`.
+//! With `--spectate`, no keys are bound to players.  With `--server`,
+//! it plays (or watches) the game on the authoritative server
+//! (`src/bin/server.rs`) with the P1 keys.
+
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{ExecutableCommand, QueueableCommand};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message as WsMessage, WebSocket};
+
+use bomberhuman::game_state::GameState;
+use bomberhuman::input_map::InputMap;
+use bomberhuman::keyboard::Key;
+use bomberhuman::tui::*;
+
+/// One frame of the game
+const FRAME: Duration = Duration::from_millis(16);
+
+/// Most terminals report only key-presses.  In that case, a key is
+/// regarded as released if no auto-repeat comes within this duration.
+const HOLD: Duration = Duration::from_millis(300);
+
+/// Raw mode terminal restored on drop
+struct RawTerminal {
+    /// Terminal reports key-release events
+    enhanced: bool,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        out.execute(EnterAlternateScreen)?
+            .execute(Hide)?
+            .execute(Clear(ClearType::All))?;
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced {
+            out.execute(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+            ))?;
+        }
+        Ok(RawTerminal { enhanced })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.enhanced {
+            let _ = out.execute(PopKeyboardEnhancementFlags);
+        }
+        let _ = out
+            .execute(Show)
+            .and_then(|o| o.execute(LeaveAlternateScreen));
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+type Server = WebSocket<MaybeTlsStream<TcpStream>>;
+
+fn connect(url: &str) -> io::Result<Server> {
+    let (ws, _) = tungstenite::connect(url).map_err(io::Error::other)?;
+    if let MaybeTlsStream::Plain(stream) = ws.get_ref() {
+        stream.set_nonblocking(true)?;
+    }
+    Ok(ws)
+}
+
+/// Exchange messages with the server without blocking
+fn communicate(ws: &mut Server, gs: &mut GameState) -> io::Result<()> {
+    loop {
+        match ws.read() {
+            Ok(WsMessage::Binary(data)) => gs.net_receive(&data).map_err(io::Error::other)?,
+            Ok(WsMessage::Close(_)) => return Err(io::Error::other("server has closed")),
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => return Err(io::Error::other(e)),
+        }
+    }
+    while let Some(data) = gs.net_poll() {
+        match ws.send(WsMessage::Binary(data)) {
+            Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => (),
+            result => result.map_err(io::Error::other)?,
+        }
+    }
+    Ok(())
+}
+
+/// Pause, step and change the speed of `gs` by `code`
+///
+/// Returns false if `code` is not for the time control.
+fn time_control(gs: &mut GameState, code: KeyCode) -> bool {
+    match code {
+        KeyCode::Enter => gs.set_paused(!gs.paused()),
+        KeyCode::Char('.') => {
+            gs.step_tick();
+        }
+        KeyCode::Char('[') => gs.set_time_scale(gs.time_scale() / 2.0),
+        KeyCode::Char(']') => gs.set_time_scale(gs.time_scale() * 2.0),
+        _ => return false,
+    }
+    true
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let spectate = args.iter().any(|a| a == "--spectate");
+    let server_url = args
+        .iter()
+        .position(|a| a == "--server")
+        .and_then(|i| args.get(i + 1));
+
+    let input_map = match args.iter().position(|a| a == "--keys") {
+        Some(i) => {
+            let path = args.get(i + 1).map(String::as_str).unwrap_or_default();
+            let json = std::fs::read_to_string(path)?;
+            InputMap::from_json(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+        None => InputMap::new(),
+    };
+
+    let mut gs = GameState::new(900, 780);
+    let mut server = match server_url {
+        Some(url) if spectate => Some(connect(&format!("{}/spectate", url))?),
+        Some(url) => Some(connect(url)?),
+        None => None,
+    };
+    if server.is_some() {
+        gs.net_watch();
+    }
+    let mut screen = TerminalScreen::for_game(&gs);
+    let term = RawTerminal::enter()?;
+    let mut out = io::stdout();
+
+    // Keys being pressed and the time of the last press
+    let mut held: Vec<(u32, Key, Instant)> = vec![];
+    let mut prev = Instant::now();
+
+    loop {
+        while event::poll(Duration::from_millis(0))? {
+            let key = match event::read()? {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.code == KeyCode::Esc || ctrl_c {
+                return Ok(());
+            }
+            if spectate {
+                continue;
+            }
+            if key.kind != KeyEventKind::Release && time_control(&mut gs, key.code) {
+                continue;
+            }
+            if let Some((bind, k)) = key_bind(&input_map, key.code) {
+                held.retain(|&(b, hk, _)| (b, hk) != (bind, k));
+                let pressed = key.kind != KeyEventKind::Release;
+                gs.toggle_key(bind, k, pressed).map_err(io::Error::other)?;
+                if pressed {
+                    held.push((bind, k, Instant::now()));
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if !term.enhanced {
+            let (pressed, released) = held
+                .into_iter()
+                .partition(|&(_, _, t)| now.duration_since(t) < HOLD);
+            held = pressed;
+            for (bind, k, _) in released {
+                gs.toggle_key(bind, k, false).map_err(io::Error::other)?;
+            }
+        }
+
+        if let Some(ws) = &mut server {
+            communicate(ws, &mut gs)?;
+        }
+        gs.update(now.duration_since(prev).as_millis() as i32);
+        prev = now;
+        // No sounds or effects in the terminal.  Drop the events not to
+        // pile them up.
+        gs.take_events();
+
+        screen.render(&gs);
+        screen.draw(&mut out, 0, 0)?;
+        out.queue(MoveTo(0, screen.height as u16 + 1))?
+            .queue(Print(if spectate {
+                "spectating  Esc: quit"
+            } else {
+                "P1: ←→↑↓ space  P2: adwsq  P3: hlkju  P4: 12345  Esc: quit"
+            }))?
+            .queue(MoveTo(0, screen.height as u16 + 2))?
+            .queue(Print(format!(
+                "{}  x{:<4}  Enter: pause  .: step  [ ]: speed",
+                if gs.paused() { "PAUSED " } else { "RUNNING" },
+                gs.time_scale()
+            )))?;
+        out.flush()?;
+
+        let elapsed = Instant::now().duration_since(now);
+        if elapsed < FRAME {
+            thread::sleep(FRAME - elapsed);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Computer players
+//!
+//! A bot reads the cells of the stage and decides its keys for the next
+//! tick: run away from blasts, bomb soft blocks and rivals when it can
+//! escape, pick up items, and otherwise walk toward such places.
+//! Ghosts in `Rules::revenge` ride the outer wall toward rivals, and
+//! throw bombs at them.
+
+use std::collections::VecDeque;
+
+use crate::actors::player::LOB_CELLS;
+use crate::game_state::GameState;
+use crate::geometry::*;
+use crate::keyboard::KeyState;
+use crate::stage::BORDER;
+use crate::*;
+
+/// Keys of `player` for the next tick
+///
+/// Players not in the game or dead press nothing.
+pub fn think(gs: &GameState, player: u32) -> KeyState {
+    let (pnt, power, can_bomb, ghost) = {
+        let players = gs.players();
+        let me = match players
+            .iter()
+            .find(|p| p.id == player && (p.alive() || p.ghost()))
+        {
+            Some(me) => me,
+            None => return KeyState::new(),
+        };
+        let placed = gs.bombs().iter().filter(|b| b.owner_id == player).count();
+        (
+            me.pnt,
+            me.bomb_power(),
+            placed < me.max_num_bombs() as usize,
+            me.ghost(),
+        )
+    };
+    let grid = Grid::new(gs, player);
+    if ghost {
+        return haunt(&grid, pnt, can_bomb);
+    }
+    let here = pnt.to_cell();
+
+    if grid.is_dangerous(here) {
+        return match grid.path(here, |c| !grid.is_dangerous(c), false) {
+            Some(next) => walk(pnt, next),
+            None => KeyState::new(),
+        };
+    }
+
+    let worth_bombing = |c: CellPos| {
+        grid.blast(c, power)
+            .iter()
+            .any(|&b| grid.is_soft(b) || grid.rivals.contains(&b))
+    };
+    if can_bomb && !grid.is_bomb(here) && worth_bombing(here) && grid.can_escape(here, power) {
+        let mut keys = KeyState::new();
+        keys.button1 = true;
+        return keys;
+    }
+
+    let goal = |c: CellPos| {
+        grid.items.contains(&c) || (can_bomb && worth_bombing(c) && grid.can_escape(c, power))
+    };
+    match grid.path(here, goal, true) {
+        Some(next) => walk(pnt, next),
+        // Stay on the center of the cell to be ready to go anywhere
+        None => walk(pnt, here),
+    }
+}
+
+/// Keys of a ghost at `pnt`: throw a bomb if a rival is near the cell
+/// where it lands, or ride the outer wall toward the nearest rival.
+fn haunt(grid: &Grid, pnt: PixelPos, can_bomb: bool) -> KeyState {
+    let here = pnt.to_cell();
+    let mut keys = KeyState::new();
+    let landing = BORDER
+        .inward(here)
+        .map(|dir| (0..LOB_CELLS).fold(here, |c, _| c.step(dir)));
+    let near = |a: CellPos, b: CellPos| (a.col - b.col).abs() + (a.row - b.row).abs() <= LOB_CELLS;
+    if can_bomb && landing.is_some_and(|l| grid.rivals.iter().any(|&r| near(r, l))) {
+        keys.button1 = true;
+        return keys;
+    }
+
+    let target = grid
+        .rivals
+        .iter()
+        .map(|&r| BORDER.nearest(r))
+        .min_by_key(|&c| BORDER.distance(here, c));
+    if let Some(target) = target {
+        // Both ways to get round the corners
+        let d = BORDER.next_toward(here, target).to_pixel() - pnt;
+        keys.left = d.x < 0;
+        keys.right = d.x > 0;
+        keys.up = d.y < 0;
+        keys.down = d.y > 0;
+    }
+    keys
+}
+
+/// Keys to walk from `pnt` toward `target`
+fn walk(pnt: PixelPos, target: CellPos) -> KeyState {
+    let d = target.to_pixel() - pnt;
+    let mut keys = KeyState::new();
+    if d.x.abs() >= d.y.abs() {
+        keys.left = d.x < 0;
+        keys.right = d.x > 0;
+    } else {
+        keys.up = d.y < 0;
+        keys.down = d.y > 0;
+    }
+    keys
+}
+
+/// Cells of the stage seen by a bot
+struct Grid {
+    cols: i32,
+    rows: i32,
+    /// Blocks and bombs in the way
+    walls: Vec<bool>,
+    soft: Vec<bool>,
+    bombs: Vec<bool>,
+    /// Cells in fire or in the blast of a bomb
+    danger: Vec<bool>,
+    fires: Vec<bool>,
+    rivals: Vec<CellPos>,
+    items: Vec<CellPos>,
+}
+
+impl Grid {
+    fn new(gs: &GameState, player: u32) -> Self {
+        let CellPos {
+            col: cols,
+            row: rows,
+        } = pnt!(gs.width as i32, gs.height as i32).to_cell();
+        let len = (cols * rows) as usize;
+        let mut grid = Grid {
+            cols,
+            rows,
+            walls: vec![false; len],
+            soft: vec![false; len],
+            bombs: vec![false; len],
+            danger: vec![false; len],
+            fires: vec![false; len],
+            rivals: vec![],
+            items: vec![],
+        };
+        for b in &*gs.blocks() {
+            if let Some(i) = grid.index(b.pnt.to_cell()) {
+                grid.walls[i] = true;
+                grid.soft[i] = b.is_soft() && !b.is_burning();
+            }
+        }
+        for b in &*gs.bombs() {
+            if let Some(i) = grid.index(b.pnt.to_cell()) {
+                grid.walls[i] = true;
+                grid.bombs[i] = true;
+            }
+        }
+        for b in &*gs.bombs() {
+            for c in grid.blast(b.pnt.to_cell(), b.power) {
+                if let Some(i) = grid.index(c) {
+                    grid.danger[i] = true;
+                }
+            }
+        }
+        for f in &*gs.fires() {
+            if let Some(i) = grid.index(f.pnt.to_cell()) {
+                grid.danger[i] = true;
+                grid.fires[i] = true;
+            }
+        }
+        grid.rivals = gs
+            .players()
+            .iter()
+            .filter(|p| p.id != player && p.alive())
+            .filter(|p| !gs.rules().teammates(p.id, player))
+            .map(|p| p.pnt.to_cell())
+            .collect();
+        grid.items = gs
+            .powers()
+            .iter()
+            .map(|p| p.pnt.to_cell())
+            .filter(|&p| grid.index(p).is_some_and(|i| !grid.walls[i]))
+            .collect();
+        grid
+    }
+
+    fn index(&self, cell: CellPos) -> Option<usize> {
+        let CellPos { col, row } = cell;
+        if col < 0 || row < 0 || col >= self.cols || row >= self.rows {
+            return None;
+        }
+        Some((row * self.cols + col) as usize)
+    }
+
+    fn is_wall(&self, cell: CellPos) -> bool {
+        self.index(cell).map_or(true, |i| self.walls[i])
+    }
+
+    fn is_soft(&self, cell: CellPos) -> bool {
+        self.index(cell).is_some_and(|i| self.soft[i])
+    }
+
+    fn is_bomb(&self, cell: CellPos) -> bool {
+        self.index(cell).is_some_and(|i| self.bombs[i])
+    }
+
+    fn is_dangerous(&self, cell: CellPos) -> bool {
+        self.index(cell).is_some_and(|i| self.danger[i])
+    }
+
+    /// Cells reached by the blast of a bomb of `power` at `cell` in the
+    /// same way as `GameState::fire`
+    fn blast(&self, cell: CellPos, power: u8) -> Vec<CellPos> {
+        let mut cells = vec![cell];
+        for &dir in &Direction::ALL {
+            let mut c = cell;
+            for _ in 0..power.saturating_sub(1) {
+                c = c.step(dir);
+                if self.is_wall(c) && !self.is_bomb(c) {
+                    if self.is_soft(c) {
+                        cells.push(c);
+                    }
+                    break;
+                }
+                cells.push(c);
+            }
+        }
+        cells
+    }
+
+    /// Predicate a safe cell is reachable after putting a bomb of
+    /// `power` at `cell`
+    fn can_escape(&self, cell: CellPos, power: u8) -> bool {
+        let blast = self.blast(cell, power);
+        self.path(
+            cell,
+            |c| !blast.contains(&c) && !self.is_dangerous(c),
+            false,
+        )
+        .is_some()
+    }
+
+    /// First cell to go on the shortest path from `start` to a cell
+    /// satisfying `goal`, or `start` itself if it is the goal
+    ///
+    /// Fires are never crossed.  With `safe`, neither are cells in
+    /// danger.
+    fn path<F: Fn(CellPos) -> bool>(&self, start: CellPos, goal: F, safe: bool) -> Option<CellPos> {
+        let start_index = self.index(start)?;
+        let mut first: Vec<Option<CellPos>> = vec![None; self.walls.len()];
+        first[start_index] = Some(start);
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(cell) = queue.pop_front() {
+            let via = first[self.index(cell)?];
+            if goal(cell) {
+                return via;
+            }
+            for (_, next) in cell.neighbors() {
+                let i = match self.index(next) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                if first[i].is_some() || self.walls[i] || self.fires[i] || (safe && self.danger[i])
+                {
+                    continue;
+                }
+                first[i] = if cell == start { Some(next) } else { via };
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+}
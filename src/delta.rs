@@ -0,0 +1,114 @@
+//! Compact difference between two snapshots
+//!
+//! The authoritative server sends the whole `GameState::snapshot`
+//! only once for each client, and then the difference from the
+//! previous one for each tick.  In most ticks, only a few timers and
+//! positions change, so the difference is much smaller.
+//!
+//! Format:
+//!
+//! ```text
+//! delta := varint(length of new) run*
+//! run   := varint(bytes to skip) varint(n) byte[n]
+//! ```
+//!
+//! where `varint` is unsigned LEB128.
+
+use crate::error::Error;
+
+/// Runs closer than this are merged, since a run costs at least two bytes.
+const MIN_GAP: usize = 3;
+
+/// Make the difference to turn `old` into `new`
+///
+/// # Examples
+///
+/// ```
+/// use bomberhuman::delta;
+///
+/// let old = b"bomberhuman".to_vec();
+/// let new = b"bomberwoman!".to_vec();
+/// let d = delta::encode(&old, &new);
+/// assert_eq!(delta::apply(&old, &d).unwrap(), new);
+/// ```
+pub fn encode(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let differs = |i: usize| i >= old.len() || old[i] != new[i];
+    let mut out = vec![];
+    write_varint(&mut out, new.len());
+
+    let mut pos = 0; // end of the last run
+    let mut i = 0;
+    while i < new.len() {
+        if !differs(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut end = i + 1;
+        // Extend the run over small gaps of the same bytes.
+        while end < new.len() && (end..(end + MIN_GAP).min(new.len())).any(differs) {
+            end += 1;
+        }
+        write_varint(&mut out, start - pos);
+        write_varint(&mut out, end - start);
+        out.extend_from_slice(&new[start..end]);
+        pos = end;
+        i = end;
+    }
+    out
+}
+
+/// Apply the difference `delta` made by `encode` to `old`
+///
+/// A broken `delta`, e.g. from a hostile peer, is an error rather than
+/// a panic.
+pub fn apply(old: &[u8], delta: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut rest = delta;
+    let len = read_varint(&mut rest)?;
+    // Bytes beyond `old` all come in runs.
+    if len > old.len().saturating_add(rest.len()) {
+        return Err(broken());
+    }
+    let mut new = old[..old.len().min(len)].to_vec();
+    new.resize(len, 0);
+
+    let mut pos: usize = 0;
+    while !rest.is_empty() {
+        let skip = read_varint(&mut rest)?;
+        let n = read_varint(&mut rest)?;
+        let start = pos.checked_add(skip).ok_or_else(broken)?;
+        let end = start.checked_add(n).ok_or_else(broken)?;
+        if end > len || n > rest.len() {
+            return Err(broken());
+        }
+        new[start..end].copy_from_slice(&rest[..n]);
+        rest = &rest[n..];
+        pos = end;
+    }
+    Ok(new)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn read_varint(data: &mut &[u8]) -> Result<usize, Error> {
+    let mut n: usize = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&b, rest) = data.split_first().ok_or_else(broken)?;
+        *data = rest;
+        n |= ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(broken())
+}
+
+fn broken() -> Error {
+    Error::InvalidMessage("broken delta".to_string())
+}
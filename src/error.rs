@@ -0,0 +1,40 @@
+//! Errors
+
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Errors reported to the callers of `GameState`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Snapshot is broken or taken by an incompatible version
+    InvalidSnapshot(String),
+    /// Message from the network is broken
+    InvalidMessage(String),
+    /// Sprite atlas descriptor is broken
+    InvalidAtlas(String),
+    /// Settings of `InputMap` are broken
+    InvalidInputMap(String),
+    /// Player id is not below `MAX_PLAYERS`
+    NoSuchPlayer(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidSnapshot(reason) => write!(f, "invalid snapshot: {}", reason),
+            Error::InvalidMessage(reason) => write!(f, "invalid message: {}", reason),
+            Error::InvalidAtlas(reason) => write!(f, "invalid atlas: {}", reason),
+            Error::InvalidInputMap(reason) => write!(f, "invalid input map: {}", reason),
+            Error::NoSuchPlayer(id) => write!(f, "no such player: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Thrown as a string on the JS side
+impl From<Error> for JsValue {
+    fn from(err: Error) -> JsValue {
+        JsValue::from_str(&err.to_string())
+    }
+}
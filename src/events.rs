@@ -0,0 +1,54 @@
+//! Game events
+//!
+//! Actors report what happened in the game through `GameState::push_event`,
+//! so that front ends can play sounds, shake the screen or update
+//! the HUD without polling the internals of actors.
+
+use serde::{Deserialize, Serialize};
+
+use crate::actors::ActorId;
+
+/// Something that happened in the game
+///
+/// Passed to JS as `{type: "BombPlaced", player: 0, x: 60, y: 60}` and so on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    /// `player` has put a bomb at (`x`, `y`)
+    BombPlaced { player: u32, x: i32, y: i32 },
+    /// Bomb of `owner` has exploded at (`x`, `y`)
+    Exploded {
+        owner: u32,
+        x: i32,
+        y: i32,
+        power: u8,
+    },
+    /// Soft block at (`x`, `y`) has caught fire
+    BlockDestroyed { x: i32, y: i32 },
+    /// `player` has picked up `item` at (`x`, `y`)
+    ItemPicked {
+        player: u32,
+        item: ActorId,
+        x: i32,
+        y: i32,
+    },
+    /// `player` has been killed at (`x`, `y`) by the fire of the bomb
+    /// of `killer`, which may be `player` itself
+    PlayerDied {
+        player: u32,
+        killer: u32,
+        x: i32,
+        y: i32,
+    },
+    /// `player` has come back at (`x`, `y`) with a life left, or
+    /// swapped in from the outer wall as a ghost
+    PlayerRespawned { player: u32, x: i32, y: i32 },
+    /// `player` out of the round has started riding the outer wall at
+    /// (`x`, `y`) as a ghost
+    BecameGhost { player: u32, x: i32, y: i32 },
+    /// At most one player or one team has survived, or the time is up
+    ///
+    /// In team battles, `winner` is the survivor of the least id in
+    /// the winning team.
+    RoundOver { winner: Option<u32> },
+}
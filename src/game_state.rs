@@ -1,24 +1,120 @@
+use rand::{RngCore, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
 use std::cell::{Ref, RefCell, RefMut};
+use std::cmp::min;
+use std::hash::Hash;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
+use crate::actors::ActorId;
+use crate::atlas::{AnimState, Animator, Atlas};
+use crate::error::Error;
+use crate::events::GameEvent;
 use crate::geometry::*;
+use crate::hash::StateHasher;
+use crate::hud::{self, PlayerStats, HUD_HEIGHT};
+use crate::rules::{Mode, Rules, MAX_PLAYERS};
 use crate::screen::*;
 use crate::*;
 use actors::block::Block;
 use actors::bomb::Bomb;
-use actors::fire::Fire;
+use actors::fire::{Fire, FireKind};
 use actors::player::Player;
 use actors::power::Power;
 use keyboard::*;
+use netplay::*;
 use stage::*;
 
+/// Version of the format of `GameState::snapshot`
+const SNAPSHOT_VERSION: u8 = 12;
+
+/// Length of one tick of the simulation in ms
+pub const TICK_MS: i32 = 16;
+
+/// Max ticks to catch up in one `update` after a long frame
+const MAX_CATCH_UP_TICKS: i32 = 8;
+
+/// Time scale of 1x in `GameState::time_scale`
+const TIME_SCALE_UNIT: i32 = 100;
+
+/// Range of `GameState::set_time_scale`
+const MIN_TIME_SCALE: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 4.0;
+
+/// Kills and suicides of a player in a round
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Score {
+    /// Rivals killed by the fire of the player's bombs
+    pub kills: u32,
+    /// Deaths by the fire of the player's own bombs, and teammates
+    /// killed by them
+    pub suicides: u32,
+}
+
+impl Score {
+    /// Kills minus suicides
+    pub fn points(&self) -> i32 {
+        self.kills as i32 - self.suicides as i32
+    }
+}
+
+/// Step of the end of a round shown for `ROUND_END_MS`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundEnd {
+    /// Still showing the end for the time left in ms
+    Showing(i32),
+    /// The next round has started.
+    NextRound,
+    /// The match is over with the winner.
+    MatchOver(u32),
+}
+
 /// Game State
 
 #[wasm_bindgen]
+#[derive(Serialize, Deserialize)]
 pub struct GameState {
     pub width: u32,
     pub height: u32,
+    /// Number of updates since the game started
+    tick: u32,
+    /// Time since the round started in ms
+    elapsed: u32,
+    rules: Rules,
+    /// Time not yet simulated in 1/`TIME_SCALE_UNIT` ms
+    accumulator: i32,
+    /// `update` does not advance the simulation
+    #[serde(skip)]
+    paused: bool,
+    /// Speed of the simulation in 1/`TIME_SCALE_UNIT`
+    #[serde(skip, default = "default_time_scale")]
+    time_scale: i32,
     key_states: Vec<KeyState>,
+    /// Input buffer in the online lockstep mode
+    lockstep: Option<Lockstep>,
+    /// Connection to the authoritative server in the remote mode
+    #[serde(skip)]
+    remote: Option<Remote>,
+    /// Events not yet drained by the front end
+    #[serde(skip)]
+    events: RefCell<Vec<GameEvent>>,
+    /// Reused by `draw` not to allocate in every frame
+    #[serde(skip)]
+    draw_list: RefCell<DrawList>,
+    /// `GameEvent::RoundOver` has been reported
+    round_over: bool,
+    /// Rounds won by each player
+    wins: Vec<u32>,
+    /// Players taking part in the next round
+    seats: Vec<bool>,
+    /// Score of each player in the round
+    scores: RefCell<Vec<Score>>,
+    /// Killers and the places of the victims in the tick, for ghosts
+    /// to swap in
+    #[serde(skip)]
+    revenges: RefCell<Vec<(u32, PixelPos)>>,
+    rng: RefCell<Pcg32>,
     blocks: RefCell<Vec<Block>>,
     bombs: RefCell<Vec<Bomb>>,
     fires: RefCell<Vec<Fire>>,
@@ -29,33 +125,432 @@ pub struct GameState {
 #[wasm_bindgen]
 impl GameState {
     pub fn new(width: u32, height: u32) -> Self {
-        let stage = Stage::new();
+        GameState::with_seed(width, height, rand::random())
+    }
+
+    /// Create game whose random events are reproducible by `seed`.
+    pub fn with_seed(width: u32, height: u32, seed: u32) -> Self {
+        GameState::with_rules(width, height, seed, Rules::default())
+    }
+
+    /// Start the next round on a new stage keeping the wins.
+    ///
+    /// Players joined or left since the last round come in or out.
+    pub fn next_round(&mut self) {
+        let mut stage = Stage::new(&mut *self.rng_mut(), &self.seats);
+        for p in &mut stage.players {
+            p.set_lives(self.rules.lives());
+        }
+
+        self.elapsed = 0;
+        self.round_over = false;
+        *self.scores.borrow_mut() = vec![Score::default(); MAX_PLAYERS as usize];
+        *self.players_mut() = stage.players;
+        *self.blocks_mut() = stage.blocks;
+        *self.powers_mut() = stage.powers;
+        self.bombs_mut().clear();
+        self.fires_mut().clear();
+    }
+
+    /// Update status of actors in the game.
+    ///
+    /// `delta` is in ms. In general, one frame takes 16.6 ms.
+    ///
+    /// To keep the simulation deterministic, actors are updated in
+    /// fixed steps of `TICK_MS`, and the remainder of `delta` is
+    /// carried over to the next call.  `delta` is stretched by the
+    /// time scale, so that slow motion takes the same ticks as
+    /// the normal speed, only spread over more frames.
+    pub fn update(&mut self, delta: i32) {
+        // The server runs the simulation in the remote mode.
+        if self.remote.is_some() || self.paused {
+            return;
+        }
+        let scaled = delta.max(0).saturating_mul(self.time_scale);
+        self.accumulator = min(
+            self.accumulator.saturating_add(scaled),
+            TICK_MS * TIME_SCALE_UNIT * MAX_CATCH_UP_TICKS,
+        );
+        while self.accumulator >= TICK_MS * TIME_SCALE_UNIT {
+            if !self.step() {
+                break;
+            }
+            self.accumulator -= TICK_MS * TIME_SCALE_UNIT;
+        }
+    }
 
-        GameState {
+    /// Stop or restart `update`.
+    ///
+    /// In the lockstep mode, the other peers wait for this one while
+    /// paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advance exactly one tick regardless of the time, e.g. to look
+    /// into a collision frame by frame while paused.
+    ///
+    /// It returns false if the tick cannot be simulated here: in the
+    /// remote mode, or while waiting for inputs in the lockstep mode.
+    pub fn step_tick(&mut self) -> bool {
+        self.remote.is_none() && self.step()
+    }
+
+    /// Run the simulation `scale` times as fast as real time.
+    ///
+    /// `scale` is clamped within 0.25 to 4.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.time_scale = (scale * TIME_SCALE_UNIT as f32).round() as i32;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale as f32 / TIME_SCALE_UNIT as f32
+    }
+
+    /// Draw all actors in the game.
+    pub fn draw(&self) {
+        let mut list = self.draw_list.borrow_mut();
+        list.clear();
+        self.draw_into(&mut list);
+        screen_flush(list.as_slice());
+    }
+
+    /// Callback function on change the key-input status
+    ///
+    /// In the online modes, `bind` 0 is for the local player, and the
+    /// others are ignored.  `bind` should be below `MAX_PLAYERS`.
+    pub fn toggle_key(&mut self, bind: u32, key: Key, state: bool) -> Result<(), Error> {
+        if bind >= MAX_PLAYERS {
+            return Err(Error::NoSuchPlayer(bind));
+        }
+        if let Some(remote) = &mut self.remote {
+            if bind == 0 {
+                remote.toggle_local_key(key, state);
+            }
+        } else if let Some(ls) = &mut self.lockstep {
+            if bind == 0 {
+                ls.toggle_local_key(key, state);
+            }
+        } else {
+            self.key_states[bind as usize].toggle(key, state);
+        }
+        Ok(())
+    }
+
+    /// Tilt the analog stick of `bind` to (`x`, `y`) within -1.0 to 1.0
+    ///
+    /// Gamepads and the on-screen joystick hold the directions of the
+    /// stick, see `KeyState::set_axis`.
+    pub fn set_axis(&mut self, bind: u32, x: f32, y: f32) -> Result<(), Error> {
+        let mut stick = KeyState::new();
+        stick.set_axis(x, y);
+        for &key in &[Key::Left, Key::Right, Key::Up, Key::Down] {
+            self.toggle_key(bind, key, stick.held(key))?;
+        }
+        Ok(())
+    }
+
+    /// Let `player` take part in the rounds from the next one, e.g.
+    /// when a new gamepad appears.
+    pub fn join(&mut self, player: u32) -> Result<(), Error> {
+        let seat = self
+            .seats
+            .get_mut(player as usize)
+            .ok_or(Error::NoSuchPlayer(player))?;
+        *seat = true;
+        Ok(())
+    }
+
+    /// Let `player` leave after the current round.  The rounds won are
+    /// kept in case of joining again.
+    pub fn leave(&mut self, player: u32) -> Result<(), Error> {
+        let seat = self
+            .seats
+            .get_mut(player as usize)
+            .ok_or(Error::NoSuchPlayer(player))?;
+        *seat = false;
+        Ok(())
+    }
+
+    /// Predicate `player` takes part in the next round
+    pub fn seated(&self, player: u32) -> bool {
+        self.seats.get(player as usize).copied().unwrap_or(false)
+    }
+
+    /// Enter the lockstep mode, and wait for the relay to start a match
+    pub fn net_join(&mut self) {
+        self.lockstep = Some(Lockstep::waiting());
+    }
+
+    /// Enter the remote mode, and draw the states streamed from the
+    /// authoritative server instead of simulating locally.
+    pub fn net_watch(&mut self) {
+        self.remote = Some(Remote::new());
+    }
+
+    /// Leave the online modes, and continue the game locally
+    pub fn net_disconnect(&mut self) {
+        self.lockstep = None;
+        self.remote = None;
+    }
+
+    /// Callback function on receiving a message from the relay
+    pub fn net_receive(&mut self, data: &[u8]) -> Result<(), Error> {
+        match Message::decode(data)? {
+            Message::Start {
+                player,
+                players,
+                seed,
+                input_delay,
+            } => {
+                let rules = Rules {
+                    players,
+                    ..Rules::default()
+                };
+                let gs = GameState::with_rules(self.width, self.height, seed, rules);
+                self.replace(gs);
+                self.lockstep = Some(Lockstep::new(player, players, input_delay));
+            }
+            Message::Input { tick, player, keys } => {
+                if let Some(ls) = &mut self.lockstep {
+                    ls.add_input(tick, player, keys);
+                }
+            }
+            Message::Leave { player } => {
+                if let Some(ls) = &mut self.lockstep {
+                    ls.disconnect(player);
+                }
+            }
+            msg @ Message::State { .. } | msg @ Message::Delta { .. } => {
+                if let Some(remote) = &mut self.remote {
+                    let gs = remote
+                        .receive_state(&msg)
+                        .and_then(GameState::from_snapshot)?;
+                    self.replace(gs);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Next message to be sent to the relay or server if any
+    pub fn net_poll(&mut self) -> Option<Vec<u8>> {
+        if let Some(remote) = &mut self.remote {
+            return remote.poll_outgoing();
+        }
+        self.lockstep.as_mut()?.poll_outgoing()
+    }
+
+    /// Take a snapshot of the whole game.
+    ///
+    /// It captures every field including actors, key states, the
+    /// random number generator and timers.  Passed to JS as `Uint8Array`.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut data = vec![SNAPSHOT_VERSION];
+        bincode::serialize_into(&mut data, self).expect("GameState should be serializable");
+        data
+    }
+
+    /// Create game from `data` taken by `snapshot`.
+    pub fn from_snapshot(data: &[u8]) -> Result<GameState, Error> {
+        match data.split_first() {
+            Some((&SNAPSHOT_VERSION, body)) => {
+                bincode::deserialize(body).map_err(|e| Error::InvalidSnapshot(e.to_string()))
+            }
+            Some((version, _)) => Err(Error::InvalidSnapshot(format!(
+                "unsupported version {}",
+                version
+            ))),
+            None => Err(Error::InvalidSnapshot("no data".to_string())),
+        }
+    }
+
+    /// Rewind or fast-forward the game to `data` taken by `snapshot`.
+    ///
+    /// The game is left untouched if `data` is invalid.
+    pub fn restore(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.replace(GameState::from_snapshot(data)?);
+        Ok(())
+    }
+
+    /// Use the sprite atlas descriptor `json` instead of the default
+    /// `sprites.json`.  See `atlas` for the format.
+    pub fn load_atlas(&self, json: &str) -> Result<(), Error> {
+        let atlas = Atlas::from_json(json)?;
+        self.draw_list.borrow_mut().set_atlas(Rc::new(atlas));
+        Ok(())
+    }
+
+    /// Number of updates since the game started
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Time left in the round in ms
+    pub fn time_left(&self) -> u32 {
+        (self.rules.round_secs * 1000).saturating_sub(self.elapsed)
+    }
+
+    /// Rounds won by `player`
+    pub fn wins(&self, player: u32) -> u32 {
+        self.wins.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Kills minus suicides of `player` in the round
+    pub fn points(&self, player: u32) -> i32 {
+        self.score(player).points()
+    }
+
+    /// Stats of the players shown in the HUD as an array of objects
+    /// such as `{id: 0, alive: true, ghost: false, lives: 1, bombs: 1,
+    /// power: 2, speed: 1, wins: 0, kills: 0, suicides: 0}`
+    pub fn stats(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.player_stats()).map_err(Into::into)
+    }
+
+    /// Take the events happened since the last call as an array of
+    /// objects such as `{type: "Exploded", owner: 0, x: 60, y: 60, power: 2}`.
+    ///
+    /// Events are left out of snapshots, so there are none in the
+    /// remote mode.
+    pub fn drain_events(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.take_events()).map_err(Into::into)
+    }
+
+    /// Cheap and stable hash of the simulation state
+    ///
+    /// Made from the positions, ttl values and stats of actors, and
+    /// the state of the random number generator.  Logging it for each
+    /// tick pinpoints the tick where two peers (or a replay and a live
+    /// run) diverged.
+    ///
+    /// Since `cleanup` shuffles actors by `swap_remove`, each actor is
+    /// hashed separately and summed up so as not to depend on the order.
+    pub fn state_hash(&self) -> u32 {
+        let mut sum = StateHasher::hash_of(&(self.tick, &self.key_states));
+        sum = sum.wrapping_add(StateHasher::hash_of(&self.rng.borrow().clone().next_u64()));
+        sum = sum.wrapping_add(sum_of_hashes(&self.blocks()));
+        sum = sum.wrapping_add(sum_of_hashes(&self.bombs()));
+        sum = sum.wrapping_add(sum_of_hashes(&self.fires()));
+        sum = sum.wrapping_add(sum_of_hashes(&self.players()));
+        sum = sum.wrapping_add(sum_of_hashes(&self.powers()));
+        (sum ^ (sum >> 32)) as u32
+    }
+}
+
+impl GameState {
+    /// Create game of `rules` whose random events are reproducible by `seed`.
+    pub fn with_rules(width: u32, height: u32, seed: u32, rules: Rules) -> Self {
+        let mut gs = GameState {
             width,
             height,
-            key_states: vec![
-                KeyState::new(),
-                KeyState::new(),
-                KeyState::new(),
-                KeyState::new(),
-            ],
-            players: RefCell::new(stage.players),
+            tick: 0,
+            elapsed: 0,
+            accumulator: 0,
+            paused: false,
+            time_scale: default_time_scale(),
+            lockstep: None,
+            remote: None,
+            events: RefCell::new(vec![]),
+            draw_list: RefCell::new(DrawList::new()),
+            round_over: false,
+            wins: vec![0; MAX_PLAYERS as usize],
+            seats: (0..MAX_PLAYERS).map(|p| p < rules.players).collect(),
+            scores: RefCell::new(vec![Score::default(); MAX_PLAYERS as usize]),
+            revenges: RefCell::new(vec![]),
+            rules,
+            rng: RefCell::new(Pcg32::seed_from_u64(seed as u64)),
+            key_states: vec![KeyState::new(); MAX_PLAYERS as usize],
+            players: RefCell::new(vec![]),
             bombs: RefCell::new(vec![]),
-            blocks: RefCell::new(stage.blocks),
+            blocks: RefCell::new(vec![]),
             fires: RefCell::new(vec![]),
-            powers: RefCell::new(stage.powers),
+            powers: RefCell::new(vec![]),
+        };
+        gs.next_round();
+        gs
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Player who has won `Rules::wins_to_match` rounds if any
+    pub fn match_winner(&self) -> Option<u32> {
+        let wins = self.rules.wins_to_match;
+        self.wins.iter().position(|&w| w >= wins).map(|p| p as u32)
+    }
+
+    /// Count down `wait`, the time left to show the end of the round,
+    /// by `delta` ms.
+    ///
+    /// When it runs out, the next round starts unless the match is
+    /// over.  Front ends start the countdown at `ROUND_END_MS` on
+    /// `GameEvent::RoundOver`.
+    pub fn round_end_step(&mut self, wait: i32, delta: i32) -> RoundEnd {
+        let wait = wait - delta.max(0);
+        if wait > 0 {
+            RoundEnd::Showing(wait)
+        } else if let Some(p) = self.match_winner() {
+            RoundEnd::MatchOver(p)
+        } else {
+            self.next_round();
+            RoundEnd::NextRound
         }
     }
 
-    /// Update status of actors in the game.
+    /// Set the keys of `player` used from the next tick, e.g. by a bot.
+    pub fn set_keys(&mut self, player: u32, keys: KeyState) {
+        if let Some(ks) = self.key_states.get_mut(player as usize) {
+            ks.set(&keys);
+        }
+    }
+
+    /// Advance the simulation by one tick.
     ///
-    /// `delta` is in ms. In general, one frame takes 16.6 ms.
-    pub fn update(&mut self, delta: i32) {
+    /// In the lockstep mode, it returns false without advancing until
+    /// the inputs of all players for the tick arrive.
+    fn step(&mut self) -> bool {
+        if let Some(ls) = &mut self.lockstep {
+            ls.sample_local(self.tick);
+            match ls.take_inputs(self.tick) {
+                Some(inputs) => self.step_with(&inputs),
+                None => return false,
+            }
+        } else {
+            self.simulate(TICK_MS);
+        }
+        true
+    }
+
+    /// Advance one tick with `inputs` of players in order of id.
+    ///
+    /// This is for driving the simulation externally, e.g. rollback
+    /// netcode, regardless of the time or the key-input status.
+    pub fn step_with(&mut self, inputs: &[KeyState]) {
+        for (ks, input) in self.key_states.iter_mut().zip(inputs) {
+            ks.set(input);
+        }
+        self.simulate(TICK_MS);
+    }
+
+    /// Update all actors by `delta` ms.
+    fn simulate(&mut self, delta: i32) {
         let gs = &self;
-        for p in &mut *self.players_mut() {
+        // One by one not to hold the borrow, so that players coming back
+        // can look at the others
+        let len = self.players().len();
+        for i in 0..len {
+            let mut p = self.players()[i].clone();
             p.update(delta, gs, &self.key_states[p.id as usize]);
+            self.players_mut()[i] = p;
         }
+        self.swap_in_ghosts();
         for b in &mut *self.bombs_mut() {
             b.update(delta, gs)
         }
@@ -68,83 +563,170 @@ impl GameState {
         for p in &mut *self.powers_mut() {
             p.update(delta, gs);
         }
-        self.cleanup()
-    }
-
-    /// Draw all actors in the game.
-    pub fn draw(&self) {
-        screen_clear_rect(0, 0, self.width, self.height);
-        for p in &*self.powers() {
-            p.draw();
-        }
-        for p in &*self.players() {
-            p.draw();
-        }
-        for b in &*self.bombs() {
-            b.draw();
-        }
-        for w in &*self.blocks() {
-            w.draw();
-        }
-        for f in &*self.fires() {
-            f.draw();
+        for ks in &mut self.key_states {
+            ks.next_tick();
         }
+        self.cleanup();
+        self.check_round_over();
+        self.tick += 1;
+        self.elapsed += delta.max(0) as u32;
     }
 
-    /// Callback function on change the key-input status
-    pub fn toggle_key(&mut self, bind: u32, key: Key, state: bool) {
-        let ks = &mut self.key_states[bind as usize];
-        match key {
-            Key::Button1 => ks.button1 = state,
-            Key::Left => ks.left = state,
-            Key::Right => ks.right = state,
-            Key::Up => ks.up = state,
-            Key::Down => ks.down = state,
-        }
-    }
-}
-
-impl GameState {
-    pub fn blocks(&self) -> Ref<Vec<Block>> {
+    pub fn blocks(&self) -> Ref<'_, Vec<Block>> {
         self.blocks.borrow()
     }
 
-    pub fn blocks_mut(&self) -> RefMut<Vec<Block>> {
+    pub fn blocks_mut(&self) -> RefMut<'_, Vec<Block>> {
         self.blocks.borrow_mut()
     }
 
-    pub fn bombs(&self) -> Ref<Vec<Bomb>> {
+    pub fn bombs(&self) -> Ref<'_, Vec<Bomb>> {
         self.bombs.borrow()
     }
 
-    pub fn bombs_mut(&self) -> RefMut<Vec<Bomb>> {
+    pub fn bombs_mut(&self) -> RefMut<'_, Vec<Bomb>> {
         self.bombs.borrow_mut()
     }
 
-    pub fn players(&self) -> Ref<Vec<Player>> {
+    pub fn players(&self) -> Ref<'_, Vec<Player>> {
         self.players.borrow()
     }
 
-    pub fn players_mut(&self) -> RefMut<Vec<Player>> {
+    pub fn players_mut(&self) -> RefMut<'_, Vec<Player>> {
         self.players.borrow_mut()
     }
 
-    pub fn fires(&self) -> Ref<Vec<Fire>> {
+    pub fn fires(&self) -> Ref<'_, Vec<Fire>> {
         self.fires.borrow()
     }
 
-    pub fn fires_mut(&self) -> RefMut<Vec<Fire>> {
+    pub fn fires_mut(&self) -> RefMut<'_, Vec<Fire>> {
         self.fires.borrow_mut()
     }
 
-    pub fn powers(&self) -> Ref<Vec<Power>> {
+    pub fn powers(&self) -> Ref<'_, Vec<Power>> {
         self.powers.borrow()
     }
 
-    pub fn powers_mut(&self) -> RefMut<Vec<Power>> {
+    pub fn powers_mut(&self) -> RefMut<'_, Vec<Power>> {
         self.powers.borrow_mut()
     }
 
+    pub fn rng_mut(&self) -> RefMut<'_, Pcg32> {
+        self.rng.borrow_mut()
+    }
+
+    /// Kills and suicides of `player` in the round
+    pub fn score(&self, player: u32) -> Score {
+        let scores = self.scores.borrow();
+        scores.get(player as usize).copied().unwrap_or_default()
+    }
+
+    /// Score the death of `victim` by the fire of the bomb of `killer`
+    ///
+    /// Killing oneself or a teammate counts as a suicide.
+    pub fn record_kill(&self, killer: u32, victim: &Player) {
+        let suicide = killer == victim.id || self.rules.teammates(killer, victim.id);
+        if let Some(score) = self.scores.borrow_mut().get_mut(killer as usize) {
+            if suicide {
+                score.suicides += 1;
+            } else {
+                score.kills += 1;
+            }
+        }
+        if !suicide && self.rules.revenge {
+            self.revenges.borrow_mut().push((killer, victim.pnt));
+        }
+    }
+
+    /// Bring the ghosts who killed rivals in the tick back to the
+    /// places of the victims
+    fn swap_in_ghosts(&self) {
+        let revenges = self.revenges.take();
+        let len = self.players().len();
+        for i in 0..len {
+            let mut p = self.players()[i].clone();
+            if let Some(&(_, pnt)) = revenges.iter().find(|(killer, _)| *killer == p.id) {
+                if p.ghost() {
+                    p.swap_in(self, pnt);
+                    self.players_mut()[i] = p;
+                }
+            }
+        }
+    }
+
+    /// Stats of the players in order of id
+    pub fn player_stats(&self) -> Vec<PlayerStats> {
+        let mut stats: Vec<PlayerStats> = self
+            .players()
+            .iter()
+            .map(|p| PlayerStats {
+                id: p.id,
+                alive: p.alive(),
+                ghost: p.ghost(),
+                lives: p.lives(),
+                bombs: p.max_num_bombs(),
+                power: p.bomb_power(),
+                speed: p.speed(),
+                wins: self.wins(p.id),
+                kills: self.score(p.id).kills,
+                suicides: self.score(p.id).suicides,
+            })
+            .collect();
+        stats.sort_by_key(|s| s.id);
+        stats
+    }
+
+    /// Sprites to draw the game
+    pub fn draw_list(&self) -> DrawList {
+        let mut list = DrawList::with_atlas(self.draw_list.borrow().atlas());
+        self.draw_into(&mut list);
+        list
+    }
+
+    /// Add the sprites of the HUD, the floor and all actors to `list`,
+    /// and sort them in the drawing order.
+    ///
+    /// The arena is drawn below the HUD strip of `HUD_HEIGHT`.
+    pub fn draw_into(&self, list: &mut DrawList) {
+        list.set_origin(0, 0);
+        hud::draw(self, list);
+        list.set_origin(0, HUD_HEIGHT);
+
+        let floor = Animator::new(ActorId::Floor, AnimState::Idle);
+        for cell in cells_in(self.width as i32, self.height as i32) {
+            let (x, y) = cell.to_pixel().to_tuple();
+            list.put_animation(Layer::Floor, x, y, &floor);
+        }
+        for p in &*self.powers() {
+            p.draw(list);
+        }
+        for p in &*self.players() {
+            p.draw(list);
+        }
+        for b in &*self.bombs() {
+            b.draw(list);
+        }
+        for w in &*self.blocks() {
+            w.draw(list);
+        }
+        for f in &*self.fires() {
+            f.draw(list);
+        }
+        list.set_origin(0, 0);
+        list.sort();
+    }
+
+    /// Report `event` to the front end
+    pub fn push_event(&self, event: GameEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    /// Take the events happened since the last call
+    pub fn take_events(&self) -> Vec<GameEvent> {
+        self.events.replace(vec![])
+    }
+
     /// Clean-up function called after update of actors
     ///
     /// Remove expired fire and bombs.
@@ -156,7 +738,14 @@ impl GameState {
             if bombs[i].alive() {
                 i += 1;
             } else {
-                self.fire(bombs[i].pnt, bombs[i].power);
+                let b = &bombs[i];
+                self.push_event(GameEvent::Exploded {
+                    owner: b.owner_id,
+                    x: b.pnt.x,
+                    y: b.pnt.y,
+                    power: b.power,
+                });
+                self.fire(b.pnt, b.power, b.owner_id);
                 bombs.swap_remove(i);
             }
         }
@@ -192,32 +781,210 @@ impl GameState {
         }
     }
 
-    /// Put fire at `(x, y)` with the `power`.
+    /// Replace the game with `gs` keeping the connection, the atlas,
+    /// the time control and the events not taken yet, which belong to
+    /// this machine rather than the game.
+    pub(crate) fn replace(&mut self, gs: GameState) {
+        let remote = self.remote.take();
+        let draw_list = self.draw_list.replace(DrawList::new());
+        let events = self.take_events();
+        let (paused, time_scale) = (self.paused, self.time_scale);
+        *self = gs;
+        self.remote = remote;
+        self.draw_list = RefCell::new(draw_list);
+        *self.events.borrow_mut() = events;
+        self.paused = paused;
+        self.time_scale = time_scale;
+    }
+
+    /// Report `GameEvent::RoundOver` once at most one player or the
+    /// players of one team survive with lives left, or the time is up.
     ///
-    /// Fire spreads into four-directions.
-    fn fire(&self, pnt: Point, power: u8) {
-        let mut fires = self.fires_mut();
-        let start = pnt.align_to_grid();
-        let mut p;
-        let mut pnt;
-        for &vec in &[pnt!(0, -GS), pnt!(0, GS), pnt!(-GS, 0), pnt!(GS, 0)] {
-            p = power;
-            pnt = start;
-            loop {
-                if p == 0 {
-                    break;
-                }
-                if let Some(block) = self.blocks().iter().find(|b| b.pnt == pnt) {
-                    if block.is_soft() {
-                        fires.push(Fire::new(pnt.x, pnt.y));
+    /// Every player of the winning team in the match wins the round.
+    ///
+    /// In `Mode::Deathmatch`, the round goes on until the time is up,
+    /// and the player or the team of the top score wins.
+    fn check_round_over(&mut self) {
+        if self.round_over {
+            return;
+        }
+        let winner = if self.rules.mode == Mode::Deathmatch {
+            if self.time_left() > 0 {
+                return;
+            }
+            self.top_scorer()
+        } else {
+            let survivors = self.ids_where(|p| !p.out());
+            let one_team = self.one_team(&survivors);
+            if !one_team && self.time_left() > 0 {
+                return;
+            }
+            survivors.first().copied().filter(|_| one_team)
+        };
+
+        self.round_over = true;
+        let present = self.ids_where(|_| true);
+        for p in 0..MAX_PLAYERS {
+            let in_match = self.seated(p) || present.contains(&p);
+            if in_match && winner.is_some_and(|w| p == w || self.rules.teammates(p, w)) {
+                self.wins[p as usize] += 1;
+            }
+        }
+        self.push_event(GameEvent::RoundOver { winner });
+    }
+
+    /// Ids of the players matching `pred` in order
+    fn ids_where<F: Fn(&Player) -> bool>(&self, pred: F) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .players()
+            .iter()
+            .filter(|p| pred(p))
+            .map(|p| p.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Predicate `ids` are all in the team of the first one
+    fn one_team(&self, ids: &[u32]) -> bool {
+        ids.iter()
+            .all(|&p| p == ids[0] || self.rules.teammates(p, ids[0]))
+    }
+
+    /// Player of the top points in the round, or the one of the least
+    /// id in the team of the top points in total.  None if tied.
+    fn top_scorer(&self) -> Option<u32> {
+        let ids = self.ids_where(|_| true);
+        let points = |p: u32| -> i32 {
+            ids.iter()
+                .filter(|&&q| q == p || self.rules.teammates(p, q))
+                .map(|&q| self.points(q))
+                .sum()
+        };
+        let best = ids.iter().map(|&p| points(p)).max()?;
+        let top: Vec<u32> = ids.iter().copied().filter(|&p| points(p) == best).collect();
+        top.first().copied().filter(|_| self.one_team(&top))
+    }
+
+    /// Cell nearest to `cell` out of blocks, bombs, fire, the blasts of
+    /// bombs and players in the arena, or `cell` itself if none
+    ///
+    /// Cells at the same distance are taken from the top-left.
+    pub fn safe_cell_near(&self, cell: CellPos) -> CellPos {
+        let unsafe_cells = self.unsafe_cells();
+        let players: Vec<PixelPos> = self
+            .players()
+            .iter()
+            .filter(|p| p.alive())
+            .map(|p| p.pnt)
+            .collect();
+        let is_safe = |c: &CellPos| {
+            !unsafe_cells.contains(c) && !players.iter().any(|p| p.collides_with(c.to_pixel()))
+        };
+
+        let CellPos {
+            col: cols,
+            row: rows,
+        } = pnt!(self.width as i32, self.height as i32).to_cell();
+        let within = |c: &CellPos| (0..cols).contains(&c.col) && (0..rows).contains(&c.row);
+        for d in 0..cols + rows {
+            let ring = (-d..=d).flat_map(|dr| {
+                let dc = d - dr.abs();
+                let row = cell.row + dr;
+                let left = cell!(cell.col - dc, row);
+                let right = cell!(cell.col + dc, row);
+                std::iter::once(left).chain(Some(right).filter(|_| dc != 0))
+            });
+            if let Some(c) = ring.filter(within).find(is_safe) {
+                return c;
+            }
+        }
+        cell
+    }
+
+    /// Cells of blocks, fire, and bombs and their blasts
+    fn unsafe_cells(&self) -> Vec<CellPos> {
+        let mut cells: Vec<CellPos> = self
+            .blocks()
+            .iter()
+            .map(|b| b.pnt.to_cell())
+            .chain(self.fires().iter().map(|f| f.pnt.to_cell()))
+            .collect();
+        for b in &*self.bombs() {
+            let start = b.pnt.to_cell();
+            cells.push(start);
+            for (_, arm) in self.arms(start, b.power) {
+                cells.extend(arm);
+            }
+        }
+        cells
+    }
+
+    /// Cells reached by the fire from `start` with the `power` in each
+    /// direction, up to a soft block or before a hard block
+    fn arms(&self, start: CellPos, power: u8) -> Vec<(Direction, Vec<CellPos>)> {
+        let reach = power.saturating_sub(1);
+        Direction::ALL
+            .iter()
+            .map(|&dir| {
+                let mut cells = vec![];
+                let mut cell = start;
+                for _ in 0..reach {
+                    cell = cell.step(dir);
+                    if let Some(block) = self.blocks().iter().find(|b| b.pnt.to_cell() == cell) {
+                        if block.is_soft() {
+                            cells.push(cell);
+                        }
+                        break;
                     }
-                    break;
-                } else {
-                    fires.push(Fire::new(pnt.x, pnt.y));
+                    cells.push(cell);
                 }
-                p -= 1;
-                pnt += vec;
+                (dir, cells)
+            })
+            .collect()
+    }
+
+    /// Put fire of the bomb of `owner` at `pnt` with the `power`.
+    ///
+    /// Fire spreads into four-directions, and each arm ends with a tip.
+    /// A fire in the same place as another merges into one piece.
+    fn fire(&self, pnt: PixelPos, power: u8, owner: u32) {
+        let start = pnt.to_cell();
+        self.put_fire(start, FireKind::Center, owner);
+
+        for (dir, cells) in self.arms(start, power) {
+            let arm = match dir {
+                Direction::N | Direction::S => FireKind::Vertical,
+                Direction::W | Direction::E => FireKind::Horizontal,
+            };
+            if let Some((&tip, body)) = cells.split_last() {
+                for &c in body {
+                    self.put_fire(c, arm, owner);
+                }
+                self.put_fire(tip, FireKind::Tip(dir), owner);
             }
         }
     }
+
+    /// Put a new fire of `kind` by `owner` in `cell` merging with the
+    /// one there.
+    fn put_fire(&self, cell: CellPos, kind: FireKind, owner: u32) {
+        let pnt = cell.to_pixel();
+        let mut fires = self.fires_mut();
+        match fires.iter_mut().find(|f| f.pnt == pnt) {
+            Some(f) => *f = f.merge(kind, owner),
+            None => fires.push(Fire::new(pnt.x, pnt.y, kind, owner)),
+        }
+    }
+}
+
+fn default_time_scale() -> i32 {
+    TIME_SCALE_UNIT
+}
+
+/// Order-independent hash of `actors`
+fn sum_of_hashes<T: Hash>(actors: &[T]) -> u64 {
+    actors
+        .iter()
+        .fold(0, |sum, a| sum.wrapping_add(StateHasher::hash_of(a)))
 }
@@ -1,9 +1,58 @@
 //! Geometry
+//!
+//! Locations come in two units: `PixelPos` on the screen, and `CellPos`
+//! on the grid of `GS` x `GS` pixels.  `Offset` is the difference of two
+//! `PixelPos` such as a move in a tick.  They convert into each other
+//! only explicitly by `PixelPos::to_cell`, `CellPos::to_pixel` and so on.
 
-use std::cmp::max;
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, Ordering};
 use std::ops::{Add, AddAssign, Mul, Sub};
 
+/// Short version of PixelPos::new(x, y)
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! pnt {
+    ($x:expr, $y:expr) => {{
+        let x: i32 = $x;
+        let y: i32 = $y;
+        $crate::geometry::PixelPos::new(x, y)
+    }};
+}
+
+/// Short version of PixelPos::new(x, y).align_to_grid();
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! grd {
+    ($x:expr, $y:expr) => {{
+        $crate::geometry::PixelPos::new($x, $y).align_to_grid()
+    }};
+}
+
+/// Short version of Offset::new(x, y)
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! off {
+    ($x:expr, $y:expr) => {{
+        let x: i32 = $x;
+        let y: i32 = $y;
+        $crate::geometry::Offset::new(x, y)
+    }};
+}
+
+/// Short version of CellPos::new(col, row)
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! cell {
+    ($col:expr, $row:expr) => {{
+        let col: i32 = $col;
+        let row: i32 = $row;
+        $crate::geometry::CellPos::new(col, row)
+    }};
+}
+
 /// Cardinal Direction  N/W/S/E
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     N,
     W,
@@ -11,6 +60,32 @@ pub enum Direction {
     E,
 }
 
+impl Direction {
+    /// All the directions counterclockwise from N
+    pub const ALL: [Direction; 4] = [Direction::N, Direction::W, Direction::S, Direction::E];
+
+    /// Offset of one pixel toward the direction
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate bomberhuman; fn main() {
+    /// use bomberhuman::geometry::*;
+    ///
+    /// assert_eq!(Direction::W.to_offset() * GS, off!(-60, 0));
+    /// assert_eq!(Direction::S.to_offset().cardinal_direction(), Some(Direction::S));
+    /// # }
+    /// ```
+    pub fn to_offset(self) -> Offset {
+        match self {
+            Direction::N => off!(0, -1),
+            Direction::W => off!(-1, 0),
+            Direction::S => off!(0, 1),
+            Direction::E => off!(1, 0),
+        }
+    }
+}
+
 /// Grid size: Width and height of each Grid
 ///
 /// In this game, almost all game characters (actors) are to be
@@ -19,11 +94,35 @@ pub enum Direction {
 /// This also means that every character has the size of GS x GS.
 pub const GS: i32 = 60;
 
-/// Point: (x, y) style location, also known as Vector or Grid
+/// Min overlap with an open lane to be nudged into it by
+/// `PixelPos::assist_corner`
+pub const CORNER_ASSIST: i32 = GS / 3;
+
+/// Cell of the grid at `col`, `row` from the top-left
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate bomberhuman; fn main() {
+/// use bomberhuman::geometry::*;
+///
+/// let cell = cell!(2, 3);
+/// assert_eq!(cell.to_pixel(), pnt!(120, 180)); // grid size (GS) is 60
+/// assert_eq!(pnt!(115, 160).to_cell(), cell);
+/// assert_eq!(cell.step(Direction::N), cell!(2, 2));
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CellPos {
+    pub col: i32,
+    pub row: i32,
+}
+
+/// Location on the screen in pixels, at the top-left of an actor
 ///
 /// Since almost all game characters (actors) are to be located on the
-/// centers of grids, Point has some alignment functions and macros
-/// such as `align_to_grid`, `grd!`, `vector_toward_grid`
+/// centers of grids, PixelPos has some alignment functions and macros
+/// such as `align_to_grid`, `grd!`, `offset_to_grid`
 ///
 /// # Examples
 ///
@@ -31,61 +130,288 @@ pub const GS: i32 = 60;
 /// # #[macro_use] extern crate bomberhuman; fn main() {
 /// use bomberhuman::geometry::*;
 ///
-/// let pnt = Point::new(0, 0);
+/// let pnt = PixelPos::new(0, 0);
 /// assert_eq!(pnt, pnt!(0, 0));
-/// assert_eq!(pnt.is_zero(), true);
 ///
 /// let grd = grd!(115, 160);
 /// assert_eq!(pnt!(120, 180), grd); // grid size (GS) is 60
 /// assert_eq!(pnt!(115, 160).align_to_grid(), grd!(120, 180));
+/// assert_eq!(pnt!(115, 160) + off!(5, 20), grd);
 /// # }
 /// ```
 ///
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Point {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PixelPos {
     pub x: i32,
     pub y: i32,
 }
 
-/// Short version of Point::new(x, y)
-#[allow(unused_macros)]
-#[macro_export]
-macro_rules! pnt {
-    ($x:expr, $y:expr) => {{
-        let x: i32 = $x;
-        let y: i32 = $y;
-        $crate::geometry::Point::new(x, y)
-    }};
+/// Difference of two `PixelPos` in pixels
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Offset {
+    pub x: i32,
+    pub y: i32,
 }
 
-/// Short version of Point::new(x, y).align_to_grid();
-#[allow(unused_macros)]
-#[macro_export]
-macro_rules! grd {
-    ($x:expr, $y:expr) => {{
-        let x: i32 = $x;
-        let y: i32 = $y;
-        pnt!((x + GS / 2) / GS * GS, (y + GS / 2) / GS * GS)
-    }};
+/// Cells covering `width` x `height` pixels from the top-left in
+/// row-major order
+pub fn cells_in(width: i32, height: i32) -> impl Iterator<Item = CellPos> {
+    let cols = (width + GS - 1) / GS;
+    let rows = (height + GS - 1) / GS;
+    (0..rows).flat_map(move |row| (0..cols).map(move |col| cell!(col, row)))
 }
 
-impl Point {
-    /// Construct Point with x, y
+impl CellPos {
+    pub fn new(col: i32, row: i32) -> Self {
+        CellPos { col, row }
+    }
+
+    /// Top-left pixel of the cell
+    pub fn to_pixel(self) -> PixelPos {
+        pnt!(self.col * GS, self.row * GS)
+    }
+
+    /// Next cell toward `dir`
+    pub fn step(self, dir: Direction) -> CellPos {
+        let d = dir.to_offset();
+        cell!(self.col + d.x, self.row + d.y)
+    }
+
+    /// Four cells next to the cell in the order of `Direction::ALL`
+    pub fn neighbors(self) -> impl Iterator<Item = (Direction, CellPos)> {
+        Direction::ALL.iter().map(move |&dir| (dir, self.step(dir)))
+    }
+}
+
+impl PixelPos {
+    /// Construct PixelPos with x, y
     pub fn new(x: i32, y: i32) -> Self {
-        Point { x, y }
+        PixelPos { x, y }
+    }
+
+    /// The nearest cell
+    pub fn to_cell(self) -> CellPos {
+        cell!(
+            (self.x + GS / 2).div_euclid(GS),
+            (self.y + GS / 2).div_euclid(GS)
+        )
     }
 
     /// Create the nearest grid point.
-    pub fn align_to_grid(&self) -> Grid {
-        grd!(self.x, self.y)
+    pub fn align_to_grid(&self) -> PixelPos {
+        self.to_cell().to_pixel()
+    }
+
+    /// Convert the point into tuple (x, y)
+    pub fn to_tuple(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// Calculate Offset toward the nearest grid point
+    pub fn offset_to_grid(&self) -> Offset {
+        self.align_to_grid() - *self
+    }
+
+    /// Predicate the point collides with `pnt`
+    pub fn collides_with(&self, pnt: PixelPos) -> bool {
+        (self.x - pnt.x).abs() < GS && (self.y - pnt.y).abs() < GS
+    }
+
+    /// Adjust the offset to go through the nearest grid
+    ///
+    /// # Algorism
+    ///
+    /// 1. Let `v = (dx, dy)` be the original offset and (gx, gy) be the
+    ///    offset from its current position (x, y) to the nearest grid.
+    /// 2. Let θ be the angle formed by (dx, dy) and (gx, gy)
+    /// 3. if θ is within ±90 degrees (inner product is 0 or greater) → (gx, gy).
+    /// 4. else, use (dx, dy)
+    ///
+    pub fn adjust_offset_to_grid(&self, v: Offset) -> Offset {
+        let gv = self.offset_to_grid();
+        let speed = v.length();
+
+        // It's not moving or already on the grid
+        if v.is_zero() || gv.is_zero() {
+            return v;
+        }
+
+        if gv * v >= 0 {
+            // Within 90 degree angle to the center of grid,
+            // make it go to the center of the grid.
+            // Clip the size of the offset to less than the original speed.
+            gv.clip_length(speed)
+        } else {
+            // It's moving away from the grid.
+            v
+        }
+    }
+
+    /// Shorten the offset to stop at the contact point with `obstacles`
+    ///
+    /// Obstacles colliding with the point already (e.g. a bomb just
+    /// put under a player) are passed through.  X and y are resolved
+    /// one after another, so that a diagonal move slides along the
+    /// side of an obstacle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate bomberhuman; fn main() {
+    /// use bomberhuman::geometry::*;
+    ///
+    /// let wall = [pnt!(120, 60)];
+    /// assert_eq!(pnt!(50, 60).clip_to_contact(off!(15, 0), &wall), off!(10, 0));
+    /// assert_eq!(pnt!(50, 60).clip_to_contact(off!(-15, 0), &wall), off!(-15, 0));
+    /// assert_eq!(pnt!(60, 0).clip_to_contact(off!(5, 5), &wall), off!(5, 0));
+    /// # }
+    /// ```
+    pub fn clip_to_contact(&self, v: Offset, obstacles: &[PixelPos]) -> Offset {
+        let ahead: Vec<&PixelPos> = obstacles
+            .iter()
+            .filter(|&&o| !self.collides_with(o))
+            .collect();
+
+        let mut dx = v.x;
+        for o in &ahead {
+            if (self.y - o.y).abs() < GS {
+                if dx > 0 && o.x >= self.x + GS {
+                    dx = dx.min(o.x - GS - self.x);
+                } else if dx < 0 && o.x <= self.x - GS {
+                    dx = dx.max(o.x + GS - self.x);
+                }
+            }
+        }
+        let x = self.x + dx;
+
+        let mut dy = v.y;
+        for o in &ahead {
+            if (x - o.x).abs() < GS {
+                if dy > 0 && o.y >= self.y + GS {
+                    dy = dy.min(o.y - GS - self.y);
+                } else if dy < 0 && o.y <= self.y - GS {
+                    dy = dy.max(o.y + GS - self.y);
+                }
+            }
+        }
+        off!(dx, dy)
+    }
+
+    /// Go along one lane for the diagonal offset `v`
+    ///
+    /// # Algorism
+    ///
+    /// 1. Let (gx, gy) be the offset from the point to the nearest grid.
+    /// 2. if |gx| < |gy|, the point is closer to the column → (0, dy).
+    /// 3. if |gx| > |gy|, the point is closer to the row → (dx, 0).
+    /// 4. else, on the grid, the one not `blocked` ahead, horizontal first.
+    ///
+    /// Offsets along an axis are returned as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate bomberhuman; fn main() {
+    /// use bomberhuman::geometry::*;
+    ///
+    /// let open = |_: CellPos| false;
+    /// assert_eq!(pnt!(60, 70).resolve_diagonal(off!(2, 2), open), off!(0, 2));
+    /// assert_eq!(pnt!(70, 60).resolve_diagonal(off!(2, 2), open), off!(2, 0));
+    /// assert_eq!(pnt!(60, 60).resolve_diagonal(off!(2, 2), open), off!(2, 0));
+    /// assert_eq!(pnt!(60, 60).resolve_diagonal(off!(2, 2), |c| c == cell!(2, 1)), off!(0, 2));
+    /// # }
+    /// ```
+    pub fn resolve_diagonal<F: Fn(CellPos) -> bool>(&self, v: Offset, blocked: F) -> Offset {
+        if v.x == 0 || v.y == 0 {
+            return v;
+        }
+        let horizontal = off!(v.x, 0);
+        let vertical = off!(0, v.y);
+        let g = self.offset_to_grid();
+        match g.x.abs().cmp(&g.y.abs()) {
+            Ordering::Less => vertical,
+            Ordering::Greater => horizontal,
+            Ordering::Equal => {
+                let cell = self.to_cell();
+                let ahead = |v: Offset| v.cardinal_direction().map(|d| cell.step(d));
+                match (ahead(horizontal), ahead(vertical)) {
+                    (Some(h), Some(v)) if blocked(h) && !blocked(v) => vertical,
+                    _ => horizontal,
+                }
+            }
+        }
     }
 
-    /// Predicate the point equals to (0, 0)
+    /// Turn the offset sideways into an open lane next to the blocked one
+    ///
+    /// # Algorism
+    ///
+    /// 1. Let `v` go straight in a cardinal direction, and the point be
+    ///    between the nearest lane L and the next lane L'.
+    /// 2. If the cell ahead in L is `blocked`, but the one in L' is not,
+    ///    and the point overlaps L' by `CORNER_ASSIST` or more,
+    ///    → go toward L' at the speed of `v`.
+    /// 3. else, None
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate bomberhuman; fn main() {
+    /// use bomberhuman::geometry::*;
+    ///
+    /// // A wall at (2, 1), but nothing at (2, 2)
+    /// let blocked = |c: CellPos| c == cell!(2, 1);
+    /// assert_eq!(pnt!(60, 85).assist_corner(off!(4, 0), blocked), Some(off!(0, 4)));
+    /// assert_eq!(pnt!(60, 65).assist_corner(off!(4, 0), blocked), None);
+    /// # }
+    /// ```
+    pub fn assist_corner<F: Fn(CellPos) -> bool>(&self, v: Offset, blocked: F) -> Option<Offset> {
+        if v.x != 0 && v.y != 0 {
+            return None;
+        }
+        let near = self.to_cell();
+        let across = *self - near.to_pixel();
+        // The first cell ahead not overlapped yet, and how far the point
+        // sticks out of the lane
+        let (ahead, side) = match v.cardinal_direction()? {
+            Direction::E => (
+                cell!(self.x.div_euclid(GS) + 1, near.row),
+                off!(0, across.y),
+            ),
+            Direction::W => (
+                cell!((self.x + GS - 1).div_euclid(GS) - 1, near.row),
+                off!(0, across.y),
+            ),
+            Direction::S => (
+                cell!(near.col, self.y.div_euclid(GS) + 1),
+                off!(across.x, 0),
+            ),
+            Direction::N => (
+                cell!(near.col, (self.y + GS - 1).div_euclid(GS) - 1),
+                off!(across.x, 0),
+            ),
+        };
+        let side_dir = side.cardinal_direction()?;
+        if side.length() >= CORNER_ASSIST && blocked(ahead) && !blocked(ahead.step(side_dir)) {
+            Some((side_dir.to_offset() * GS - side).clip_length(v.length()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Offset {
+    /// Construct Offset with x, y
+    pub fn new(x: i32, y: i32) -> Self {
+        Offset { x, y }
+    }
+
+    /// Predicate the offset equals to (0, 0)
     pub fn is_zero(&self) -> bool {
         self.x == 0 && self.y == 0
     }
 
-    /// Length of Vector
+    /// Length of Offset
     pub fn length(&self) -> i32 {
         max(self.x.abs(), self.y.abs())
     }
@@ -105,10 +431,10 @@ impl Point {
         }
     }
 
-    /// Clip the norm of Vector into `length`
-    pub fn clip_length(&self, length: i32) -> Vector {
+    /// Clip the norm of Offset into `length`
+    pub fn clip_length(&self, length: i32) -> Offset {
         let (x, y) = self.to_tuple();
-        pnt!(
+        off!(
             if x.abs() > length {
                 x.signum() * length
             } else {
@@ -122,97 +448,69 @@ impl Point {
         )
     }
 
-    /// Convert the point into tuple (x, y)
+    /// Convert the offset into tuple (x, y)
     pub fn to_tuple(&self) -> (i32, i32) {
         (self.x, self.y)
     }
+}
 
-    /// Calculate Vector toward the nearest grid point
-    pub fn vector_toward_grid(&self) -> Vector {
-        self.align_to_grid() - *self
-    }
+/// Implements the '+' operator for PixelPos + Offset
+impl Add<Offset> for PixelPos {
+    type Output = PixelPos;
 
-    /// Predicate the point collides with `pnt`
-    pub fn collides_with(&self, pnt: Point) -> bool {
-        (self.x - pnt.x).abs() < GS && (self.y - pnt.y).abs() < GS
+    fn add(self, rhs: Offset) -> PixelPos {
+        pnt!(self.x + rhs.x, self.y + rhs.y)
     }
+}
 
-    /// Adjust the vector to go through the nearest grid
-    ///
-    /// # Algorism
-    ///
-    /// 1. Let `v = (dx, dy)` be the original vector and (gx, gy) be the
-    ///    vector from its current position (x, y) to the nearest grid.
-    /// 2. Let θ be the angle formed by (dx, dy) and (gx, gy)
-    /// 3. if θ is within ±90 degrees (inner product is 0 or greater) → (gx, gy).
-    /// 4. else, use (dx, dy)
-    ///
-    pub fn adjust_vector_to_grid(&self, v: Vector) -> Vector {
-        let gv = self.vector_toward_grid();
-        let speed = v.length();
-
-        // It's not moving or already on the grid
-        if v.is_zero() || gv.is_zero() {
-            return v;
-        }
-
-        if gv * v >= 0 {
-            // Within 90 degree angle to the center of grid,
-            // make it go to the center of the grid.
-            // Clip the size of the vector to less than the original speed.
-            gv.clip_length(speed)
-        } else {
-            // It's moving away from the grid.
-            v
-        }
+/// Implements the '+=' operator for PixelPos += Offset
+impl AddAssign<Offset> for PixelPos {
+    fn add_assign(&mut self, rhs: Offset) {
+        *self = *self + rhs;
     }
 }
 
-/// Implements the '+' operator for Point + Point
-impl Add for Point {
-    type Output = Point;
+/// Implements the '-' operator for PixelPos - PixelPos
+impl Sub for PixelPos {
+    type Output = Offset;
 
-    fn add(self, rhs: Point) -> Point {
-        Point {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+    fn sub(self, rhs: PixelPos) -> Offset {
+        off!(self.x - rhs.x, self.y - rhs.y)
     }
 }
 
-/// Implements the '+=' operator for Point += Point
-impl AddAssign for Point {
-    fn add_assign(&mut self, rhs: Point) {
-        *self = Point {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        };
+/// Implements the '+' operator for Offset + Offset
+impl Add for Offset {
+    type Output = Offset;
+
+    fn add(self, rhs: Offset) -> Offset {
+        off!(self.x + rhs.x, self.y + rhs.y)
     }
 }
 
-/// Implements the '-' operator for Point + Point
-impl Sub for Point {
-    type Output = Point;
+/// Implements the '-' operator for Offset - Offset
+impl Sub for Offset {
+    type Output = Offset;
 
-    fn sub(self, rhs: Point) -> Point {
-        Point {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-        }
+    fn sub(self, rhs: Offset) -> Offset {
+        off!(self.x - rhs.x, self.y - rhs.y)
     }
 }
 
-/// Implements the '*' (Inner Product) operator for Point * Point
-impl Mul for Point {
+/// Implements the '*' (Inner Product) operator for Offset * Offset
+impl Mul for Offset {
     type Output = i32;
 
-    fn mul(self, rhs: Point) -> i32 {
+    fn mul(self, rhs: Offset) -> i32 {
         self.x * rhs.x + self.y * rhs.y
     }
 }
 
-/// Grid
-pub type Grid = Point;
+/// Implements the '*' operator for Offset * i32 to scale the offset
+impl Mul<i32> for Offset {
+    type Output = Offset;
 
-/// Vector
-pub type Vector = Point;
+    fn mul(self, rhs: i32) -> Offset {
+        off!(self.x * rhs, self.y * rhs)
+    }
+}
@@ -0,0 +1,87 @@
+//! Stable hash of the simulation state
+
+use std::hash::{Hash, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a hasher which gives the same result on every platform
+///
+/// `std::collections::hash_map::DefaultHasher` is not guaranteed to be
+/// stable across Rust releases, and `Hash` of integers depends on the
+/// byte order and the width of `usize`.  Here, every integer is fed
+/// as little-endian, and `usize`/`isize` as 64-bit.
+///
+/// # Examples
+///
+/// ```
+/// use bomberhuman::hash::StateHasher;
+///
+/// assert_eq!(StateHasher::hash_of(&(1, 2)), StateHasher::hash_of(&(1, 2)));
+/// assert_ne!(StateHasher::hash_of(&(1, 2)), StateHasher::hash_of(&(2, 1)));
+/// ```
+pub struct StateHasher(u64);
+
+impl StateHasher {
+    pub fn new() -> Self {
+        StateHasher(FNV_OFFSET_BASIS)
+    }
+
+    /// Hash of a single value
+    pub fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = StateHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        StateHasher::new()
+    }
+}
+
+impl Hasher for StateHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64)
+    }
+}
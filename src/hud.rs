@@ -0,0 +1,137 @@
+//! Head-up display above the arena
+//!
+//! A strip of `HUD_HEIGHT` shows the portrait and the stats of each
+//! player, and the time left in the round.  With more than four
+//! players, the panels get narrow and show the wins only.  In team
+//! battles, the floor of each panel is in the colors of the team.  In
+//! deathmatches, the points of the round are shown instead of the wins
+//! and the speed.  Text is drawn with the bitmap font of the sprite
+//! sheet (`Font` in `sprites.json`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::actors::ActorId;
+use crate::atlas::{AnimState, Animator};
+use crate::game_state::GameState;
+use crate::geometry::{cells_in, GS};
+use crate::rules::{Mode, MAX_PLAYERS};
+use crate::screen::{DrawList, Layer};
+
+/// Height of the strip
+pub const HUD_HEIGHT: i32 = 60;
+
+/// Width of a glyph of the font
+pub const GLYPH_WIDTH: i32 = 20;
+
+/// Height of a line of text
+pub const GLYPH_HEIGHT: i32 = 30;
+
+/// Width of the stats of each player up to four players
+const PANEL_WIDTH: i32 = 190;
+
+/// Width of the portrait and the wins of each player with more players
+const NARROW_PANEL_WIDTH: i32 = 95;
+
+/// Palettes of the front end from 1 to color the teams
+const TEAM_PALETTES: u32 = 4;
+
+/// Width of the round timer in the middle
+const TIMER_WIDTH: i32 = 140;
+
+/// Stats of a player shown in the HUD
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub id: u32,
+    pub alive: bool,
+    /// Riding the outer wall in `Rules::revenge`
+    pub ghost: bool,
+    /// Lives left in the round including the current one
+    pub lives: u32,
+    /// Max number of bombs put at once
+    pub bombs: u8,
+    /// Cells reached by the fire
+    pub power: u8,
+    pub speed: u8,
+    /// Rounds won
+    pub wins: u32,
+    /// Rivals killed in the round
+    pub kills: u32,
+    /// Deaths by own bombs and teammates killed in the round
+    pub suicides: u32,
+}
+
+/// Add the HUD of `gs` to `list` at the origin
+pub fn draw(gs: &GameState, list: &mut DrawList) {
+    let floor = Animator::new(ActorId::Floor, AnimState::Idle);
+    for cell in cells_in(gs.width as i32, HUD_HEIGHT) {
+        let (x, y) = cell.to_pixel().to_tuple();
+        list.put_animation(Layer::Floor, x, y, &floor);
+    }
+
+    let panels = if gs.players().iter().all(|p| p.id < 4) {
+        4
+    } else {
+        MAX_PLAYERS
+    };
+    let width = panel_x(1, panels);
+    for p in &*gs.players() {
+        let x = panel_x(p.id, panels);
+        if let Some(team) = gs.rules().team(p.id) {
+            let palette = team % TEAM_PALETTES + 1;
+            let mut left = x;
+            while left < x + width {
+                let tile = left.min(x + width - GS);
+                list.put_animation_in(Layer::Floor, tile, 0, &floor, palette);
+                left += GS;
+            }
+        }
+        let state = if p.alive() {
+            AnimState::StandS
+        } else {
+            AnimState::Dead
+        };
+        list.put_animation(Layer::Hud, x, 0, &Animator::new(p.actor_id(), state));
+
+        let deathmatch = gs.rules().mode == Mode::Deathmatch;
+        if panels == 4 {
+            let line1 = format!("b{} f{}", p.max_num_bombs(), p.bomb_power());
+            let line2 = if deathmatch {
+                format!("{}PT", gs.points(p.id))
+            } else {
+                format!("s{} w{}", p.speed(), gs.wins(p.id))
+            };
+            list.put_text(Layer::Hud, x + GS, 0, &line1, GLYPH_WIDTH);
+            list.put_text(Layer::Hud, x + GS, GLYPH_HEIGHT, &line2, GLYPH_WIDTH);
+        } else {
+            let score = if deathmatch {
+                gs.points(p.id)
+            } else {
+                gs.wins(p.id) as i32
+            };
+            let score = score.to_string();
+            list.put_text(Layer::Hud, x + GS, GLYPH_HEIGHT / 2, &score, GLYPH_WIDTH);
+        }
+    }
+
+    let secs = gs.time_left().div_ceil(1000);
+    let timer = format!("{}:{:02}", secs / 60, secs % 60);
+    let x = panel_x(panels / 2, panels) - TIMER_WIDTH;
+    let x = x + (TIMER_WIDTH - timer.len() as i32 * GLYPH_WIDTH) / 2;
+    list.put_text(Layer::Hud, x, GLYPH_HEIGHT / 2, &timer, GLYPH_WIDTH);
+}
+
+/// Left of the stats of `player` in `panels` panels: half of them on
+/// each side of the timer
+fn panel_x(player: u32, panels: u32) -> i32 {
+    let width = if panels == 4 {
+        PANEL_WIDTH
+    } else {
+        NARROW_PANEL_WIDTH
+    };
+    let x = player as i32 * width;
+    if player < panels / 2 {
+        x
+    } else {
+        x + TIMER_WIDTH
+    }
+}
@@ -0,0 +1,161 @@
+//! Bindings from keys of the keyboard to `Key`s of players
+//!
+//! Keys are named by `KeyboardEvent.key` of the browser such as
+//! `"ArrowLeft"`, `"a"` and `" "`, and the terminal version names its
+//! keys in the same way.  Any number of keys can be bound to the same
+//! `Key` of a player.
+//!
+//! The bindings are saved as a JSON settings blob:
+//!
+//! ```json
+//! {
+//!   "ArrowLeft": { "player": 0, "key": "Left" },
+//!   "a": { "player": 1, "key": "Left" }
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::keyboard::Key;
+use crate::rules::MAX_PLAYERS;
+
+/// `Key` of `player` bound to a key of the keyboard
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub player: u32,
+    pub key: Key,
+}
+
+/// Bindings of the keyboard
+///
+/// # Examples
+///
+/// ```
+/// use bomberhuman::input_map::InputMap;
+/// use bomberhuman::keyboard::Key;
+///
+/// let mut map = InputMap::new();
+/// map.bind("x", 0, Key::Button1).unwrap();
+/// assert_eq!(map.lookup("x").unwrap().key, Key::Button1);
+/// assert_eq!(map.lookup(" ").unwrap().key, Key::Button1);
+///
+/// map.rebind("z", 0, Key::Button1).unwrap();
+/// assert!(map.bind("x", 9, Key::Button1).is_err());
+/// assert_eq!(map.inputs(0, Key::Button1), vec!["z"]);
+///
+/// let saved = InputMap::from_json(&map.to_json()).unwrap();
+/// assert_eq!(saved, map);
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InputMap {
+    bindings: BTreeMap<String, Binding>,
+}
+
+impl Default for InputMap {
+    /// Keys of four players on one keyboard, and Enter or Escape for
+    /// `Key::Start`
+    fn default() -> Self {
+        let mut map = InputMap::empty();
+        let players: [[&str; 5]; 4] = [
+            ["ArrowLeft", "ArrowRight", "ArrowUp", "ArrowDown", " "],
+            ["a", "d", "w", "s", "q"],
+            ["h", "l", "k", "j", "u"],
+            ["1", "2", "3", "4", "5"],
+        ];
+        let keys = [Key::Left, Key::Right, Key::Up, Key::Down, Key::Button1];
+        let mut bindings: Vec<(&str, u32, Key)> =
+            vec![("Enter", 0, Key::Start), ("Escape", 0, Key::Start)];
+        for (player, inputs) in players.iter().enumerate() {
+            for (input, &key) in inputs.iter().zip(&keys) {
+                bindings.push((input, player as u32, key));
+            }
+        }
+        for (input, player, key) in bindings {
+            map.bind(input, player, key)
+                .expect("default bindings should be of valid players");
+        }
+        map
+    }
+}
+
+#[wasm_bindgen]
+impl InputMap {
+    /// Default bindings
+    pub fn new() -> Self {
+        InputMap::default()
+    }
+
+    /// No bindings at all
+    pub fn empty() -> Self {
+        InputMap {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// Load the bindings saved by `to_json`
+    ///
+    /// Bindings to players not below `MAX_PLAYERS` are rejected.
+    pub fn from_json(json: &str) -> Result<InputMap, Error> {
+        let map: InputMap =
+            serde_json::from_str(json).map_err(|e| Error::InvalidInputMap(e.to_string()))?;
+        match map.bindings.values().find(|b| b.player >= MAX_PLAYERS) {
+            Some(b) => Err(Error::NoSuchPlayer(b.player)),
+            None => Ok(map),
+        }
+    }
+
+    /// Save the bindings, e.g. in `localStorage`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("InputMap should be serializable")
+    }
+
+    /// Binding of `input` if any
+    pub fn lookup(&self, input: &str) -> Option<Binding> {
+        self.bindings.get(input).copied()
+    }
+
+    /// Bind `input` to `key` of `player` in addition to the other
+    /// inputs bound to it
+    ///
+    /// Players not below `MAX_PLAYERS` are rejected.
+    pub fn bind(&mut self, input: &str, player: u32, key: Key) -> Result<(), Error> {
+        if player >= MAX_PLAYERS {
+            return Err(Error::NoSuchPlayer(player));
+        }
+        self.bindings
+            .insert(input.to_string(), Binding { player, key });
+        Ok(())
+    }
+
+    /// Bind `input` to `key` of `player` instead of the other inputs
+    ///
+    /// Players not below `MAX_PLAYERS` are rejected.
+    pub fn rebind(&mut self, input: &str, player: u32, key: Key) -> Result<(), Error> {
+        if player >= MAX_PLAYERS {
+            return Err(Error::NoSuchPlayer(player));
+        }
+        let binding = Binding { player, key };
+        self.bindings.retain(|_, b| *b != binding);
+        self.bind(input, player, key)
+    }
+
+    pub fn unbind(&mut self, input: &str) {
+        self.bindings.remove(input);
+    }
+
+    /// Inputs bound to `key` of `player`
+    pub fn inputs(&self, player: u32, key: Key) -> Vec<String> {
+        let binding = Binding { player, key };
+        self.bindings
+            .iter()
+            .filter(|(_, &b)| b == binding)
+            .map(|(input, _)| input.clone())
+            .collect()
+    }
+}
@@ -28,15 +28,41 @@ class Sprite {
 let ctx = document.getElementById('canvas').getContext('2d');
 let sprites = new Sprite('assets/sprites.png');
 
-export function screen_clear_rect(x, y, width, height) {
-  ctx.clearRect(x, y, width, height);
+/// Colors changed by the palettes of `sprites.json` from 1
+const palettes = [
+  "hue-rotate(180deg)",
+  "hue-rotate(90deg)",
+  "grayscale(1) brightness(1.4)",
+  "invert(1)",
+];
+
+/// Sprite sheets in each palette, made at the first use
+let sheets = [];
+
+function sheet_in(palette) {
+  if (palette == 0 || !sprites.image.complete) return sprites.image;
+  if (!sheets[palette]) {
+    const canvas = document.createElement('canvas');
+    canvas.width = sprites.image.width;
+    canvas.height = sprites.image.height;
+    const c = canvas.getContext('2d');
+    c.filter = palettes[(palette - 1) % palettes.length];
+    c.drawImage(sprites.image, 0, 0);
+    sheets[palette] = canvas;
+  }
+  return sheets[palette];
 }
 
-/// put sprite on x,y
-export function screen_put_sprite(x, y, class_id, action = 0) {
-  ctx.drawImage(sprites.image,
-                dimension * action, dimension * class_id,
-                dimension, dimension,
-                x, y,
-                dimension, dimension);
+/// clear the screen and draw packed [x, y, row, col, palette, ...] commands
+///
+/// `cmds` is an Int32Array on the WASM memory, valid only in this call.
+export function screen_flush(cmds) {
+  ctx.clearRect(0, 0, ctx.canvas.width, ctx.canvas.height);
+  for (let i = 0; i < cmds.length; i += 5) {
+    ctx.drawImage(sheet_in(cmds[i + 4]),
+                  dimension * cmds[i + 3], dimension * cmds[i + 2],
+                  dimension, dimension,
+                  cmds[i], cmds[i + 1],
+                  dimension, dimension);
+  }
 }
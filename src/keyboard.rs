@@ -1,25 +1,51 @@
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 /// Keyboard
 
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Key {
     Button1 = 0,
     Left = 1,
     Right = 2,
     Up = 4,
     Down = 8,
+    /// Pause and menus, not used in the simulation
+    Start = 16,
 }
 
+/// Keys held down, and those in the last tick to find the edges
+///
+/// Fields are the keys held now.  `pressed` and `released` compare
+/// them with the keys at `next_tick`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct KeyState {
     pub button1: bool,
     pub left: bool,
     pub right: bool,
     pub up: bool,
     pub down: bool,
+    pub start: bool,
+    /// Keys held in the last tick in the format of `to_bits`
+    last: u8,
 }
 
+/// Analog sticks tilted less than this are at rest
+pub const DEAD_ZONE: f32 = 0.25;
+
+/// tan(22.5°): sticks within 22.5° of an axis go straight along it,
+/// and the others go diagonally
+const DIAGONAL_SLOPE: f32 = 0.414;
+
+/// Bit assigned to `button1` in `KeyState::to_bits`
+///
+/// Directions use the same values as `Key`.
+const BUTTON1_BIT: u8 = 16;
+
+/// Bit assigned to `start` in `KeyState::to_bits`
+const START_BIT: u8 = 32;
+
 impl KeyState {
     pub fn new() -> Self {
         KeyState {
@@ -28,10 +54,174 @@ impl KeyState {
             right: false,
             up: false,
             down: false,
+            start: false,
+            last: 0,
+        }
+    }
+
+    /// Predicate `key` is held down
+    pub fn held(&self, key: Key) -> bool {
+        match key {
+            Key::Button1 => self.button1,
+            Key::Left => self.left,
+            Key::Right => self.right,
+            Key::Up => self.up,
+            Key::Down => self.down,
+            Key::Start => self.start,
+        }
+    }
+
+    /// Predicate `key` got pressed since the last tick
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::keyboard::*;
+    ///
+    /// let mut ks = KeyState::new();
+    /// ks.toggle(Key::Button1, true);
+    /// assert!(ks.pressed(Key::Button1));
+    /// ks.next_tick();
+    /// assert!(ks.held(Key::Button1) && !ks.pressed(Key::Button1));
+    /// ks.toggle(Key::Button1, false);
+    /// assert!(ks.released(Key::Button1));
+    /// ```
+    pub fn pressed(&self, key: Key) -> bool {
+        self.held(key) && self.last & bit_of(key) == 0
+    }
+
+    /// Predicate `key` got released since the last tick
+    pub fn released(&self, key: Key) -> bool {
+        !self.held(key) && self.last & bit_of(key) != 0
+    }
+
+    /// Remember the keys held now to find the edges in the next tick
+    pub fn next_tick(&mut self) {
+        self.last = self.to_bits();
+    }
+
+    /// Hold the same keys as `keys` keeping the edges of this one
+    pub fn set(&mut self, keys: &KeyState) {
+        *self = KeyState {
+            last: self.last,
+            ..keys.clone()
+        };
+    }
+
+    /// Hold the directions of an analog stick at (`x`, `y`) within
+    /// -1.0 to 1.0, where y goes down
+    ///
+    /// The stick is split into eight ways out of `DEAD_ZONE`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::keyboard::*;
+    ///
+    /// let mut ks = KeyState::new();
+    /// ks.set_axis(0.1, -0.2);
+    /// assert_eq!(ks.axis(), (0, 0));
+    /// ks.set_axis(0.9, -0.2);
+    /// assert_eq!(ks.axis(), (1, 0));
+    /// ks.set_axis(0.6, -0.5);
+    /// assert_eq!(ks.axis(), (1, -1));
+    /// ```
+    pub fn set_axis(&mut self, x: f32, y: f32) {
+        let moving = x * x + y * y >= DEAD_ZONE * DEAD_ZONE;
+        let horizontal = moving && x.abs() > y.abs() * DIAGONAL_SLOPE;
+        let vertical = moving && y.abs() > x.abs() * DIAGONAL_SLOPE;
+        self.left = horizontal && x < 0.0;
+        self.right = horizontal && x > 0.0;
+        self.up = vertical && y < 0.0;
+        self.down = vertical && y > 0.0;
+    }
+
+    /// Direction of the move by -1, 0 or 1 for x and y
+    ///
+    /// Opposite directions held together cancel each other out, so
+    /// that neither wins by the order of the checks.
+    ///
+    /// ```
+    /// use bomberhuman::keyboard::*;
+    ///
+    /// let mut ks = KeyState::new();
+    /// ks.toggle(Key::Left, true);
+    /// ks.toggle(Key::Down, true);
+    /// assert_eq!(ks.axis(), (-1, 1));
+    /// ks.toggle(Key::Right, true);
+    /// assert_eq!(ks.axis(), (0, 1));
+    /// ```
+    pub fn axis(&self) -> (i32, i32) {
+        (
+            self.right as i32 - self.left as i32,
+            self.down as i32 - self.up as i32,
+        )
+    }
+
+    /// Change the status of `key`
+    pub fn toggle(&mut self, key: Key, state: bool) {
+        match key {
+            Key::Button1 => self.button1 = state,
+            Key::Left => self.left = state,
+            Key::Right => self.right = state,
+            Key::Up => self.up = state,
+            Key::Down => self.down = state,
+            Key::Start => self.start = state,
+        }
+    }
+
+    /// Pack the keys held into one byte to send over the network
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::keyboard::*;
+    ///
+    /// let mut ks = KeyState::new();
+    /// ks.toggle(Key::Left, true);
+    /// ks.toggle(Key::Button1, true);
+    /// assert_eq!(KeyState::from_bits(ks.to_bits()), ks);
+    /// ```
+    pub fn to_bits(&self) -> u8 {
+        let mut bits = 0;
+        for &key in &[
+            Key::Left,
+            Key::Right,
+            Key::Up,
+            Key::Down,
+            Key::Button1,
+            Key::Start,
+        ] {
+            if self.held(key) {
+                bits |= bit_of(key);
+            }
+        }
+        bits
+    }
+
+    /// Unpack from `to_bits` with no keys held in the last tick
+    pub fn from_bits(bits: u8) -> Self {
+        KeyState {
+            button1: bits & BUTTON1_BIT != 0,
+            left: bits & Key::Left as u8 != 0,
+            right: bits & Key::Right as u8 != 0,
+            up: bits & Key::Up as u8 != 0,
+            down: bits & Key::Down as u8 != 0,
+            start: bits & START_BIT != 0,
+            last: 0,
         }
     }
 }
 
+/// Bit assigned to `key` in `KeyState::to_bits`
+fn bit_of(key: Key) -> u8 {
+    match key {
+        Key::Button1 => BUTTON1_BIT,
+        Key::Start => START_BIT,
+        _ => key as u8,
+    }
+}
+
 impl Default for KeyState {
     fn default() -> Self {
         KeyState::new()
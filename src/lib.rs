@@ -2,12 +2,27 @@
 pub mod utils;
 
 pub mod actors;
+pub mod atlas;
+pub mod audio;
+pub mod bot;
+pub mod delta;
+pub mod error;
+pub mod events;
 pub mod game_state;
 #[macro_use]
 pub mod geometry;
+pub mod hash;
+pub mod hud;
+pub mod input_map;
 pub mod keyboard;
+pub mod netplay;
+pub mod rollback;
+pub mod rules;
+pub mod scene;
 pub mod screen;
 pub mod stage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tui;
 
 use wasm_bindgen::prelude::*;
 
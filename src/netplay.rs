@@ -0,0 +1,238 @@
+//! Online multiplayer
+//!
+//! In the lockstep mode, every peer runs the same deterministic
+//! simulation, and exchanges only the key inputs through the relay
+//! server (`src/bin/relay.rs`).  A tick is simulated after the inputs
+//! of all players for the tick have arrived.
+//!
+//! To hide the network latency, a local input sampled at tick N is
+//! applied at tick N + `input_delay`.
+//!
+//! In the remote mode, the authoritative server (`src/bin/server.rs`)
+//! runs the only simulation.  Clients send their key inputs, and
+//! just draw the states streamed from the server.  `GameEvent`s are
+//! not part of the states, so clients in the remote mode get no
+//! events to play sounds or effects.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::delta;
+use crate::error::Error;
+use crate::keyboard::*;
+
+/// Messages between the relay and the clients
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    /// relay → client: Every player has joined. Start the match.
+    Start {
+        /// Player controlled by the receiver
+        player: u32,
+        players: u32,
+        seed: u32,
+        input_delay: u32,
+    },
+    /// Both directions: Key input of `player` at `tick`
+    Input { tick: u32, player: u32, keys: u8 },
+    /// relay → client: `player` has disconnected
+    Leave { player: u32 },
+    /// server → client: Whole game at `tick` taken by `GameState::snapshot`
+    State { tick: u32, data: Vec<u8> },
+    /// server → client: Game at `tick` as `delta` from the last state
+    Delta { tick: u32, data: Vec<u8> },
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("Message should be serializable")
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Message, Error> {
+        bincode::deserialize(data).map_err(|e| Error::InvalidMessage(e.to_string()))
+    }
+}
+
+/// Input buffer of the lockstep mode
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Lockstep {
+    /// Player controlled on this machine
+    pub local_player: u32,
+    /// Ticks between sampling a local input and applying it
+    pub input_delay: u32,
+    /// Keys pressed on this machine
+    local_keys: KeyState,
+    /// Players still in the match
+    connected: Vec<bool>,
+    /// `inputs[i][p]` is the input of player `p` at tick `base_tick + i`
+    inputs: VecDeque<Vec<Option<u8>>>,
+    base_tick: u32,
+    /// The local input is sampled up to this tick
+    sampled_tick: u32,
+    /// Messages to be sent to the relay
+    outgoing: VecDeque<Vec<u8>>,
+}
+
+impl Lockstep {
+    /// Start lockstep from tick 0 with `players` players.
+    ///
+    /// Nobody can send inputs for the first `input_delay` ticks, so
+    /// they are filled with empty inputs.
+    pub fn new(local_player: u32, players: u32, input_delay: u32) -> Self {
+        let mut ls = Lockstep {
+            local_player,
+            input_delay,
+            local_keys: KeyState::new(),
+            connected: vec![true; players as usize],
+            inputs: VecDeque::new(),
+            base_tick: 0,
+            sampled_tick: input_delay,
+            outgoing: VecDeque::new(),
+        };
+        for tick in 0..input_delay {
+            for player in 0..players {
+                ls.add_input(tick, player, 0);
+            }
+        }
+        ls
+    }
+
+    /// Waiting for the relay to start the match
+    pub fn waiting() -> Self {
+        Lockstep::new(0, 0, 0)
+    }
+
+    /// Number of players in the match including disconnected ones
+    pub fn players(&self) -> u32 {
+        self.connected.len() as u32
+    }
+
+    /// Change the status of a local key
+    pub fn toggle_local_key(&mut self, key: Key, state: bool) {
+        self.local_keys.toggle(key, state);
+    }
+
+    /// Record the input of `player` at `tick`
+    ///
+    /// Inputs for ticks already simulated are ignored.
+    pub fn add_input(&mut self, tick: u32, player: u32, keys: u8) {
+        if tick < self.base_tick || player >= self.players() {
+            return;
+        }
+        let index = (tick - self.base_tick) as usize;
+        while self.inputs.len() <= index {
+            self.inputs.push_back(vec![None; self.connected.len()]);
+        }
+        self.inputs[index][player as usize] = Some(keys);
+    }
+
+    /// Stop waiting for inputs of `player`
+    pub fn disconnect(&mut self, player: u32) {
+        if let Some(c) = self.connected.get_mut(player as usize) {
+            *c = false;
+        }
+    }
+
+    /// Sample the local keys for `tick` + `input_delay` once per tick,
+    /// and queue them to be sent.
+    pub fn sample_local(&mut self, tick: u32) {
+        let target = tick + self.input_delay;
+        if self.players() == 0 || target < self.sampled_tick {
+            return;
+        }
+        let keys = self.local_keys.to_bits();
+        self.add_input(target, self.local_player, keys);
+        self.outgoing.push_back(
+            Message::Input {
+                tick: target,
+                player: self.local_player,
+                keys,
+            }
+            .encode(),
+        );
+        self.sampled_tick = target + 1;
+    }
+
+    /// Inputs of all players at `tick` if every connected player's has
+    /// arrived.  Disconnected players have no keys pressed unless their
+    /// inputs have arrived.
+    ///
+    /// Each peer learns of a disconnection at its own tick, so inputs
+    /// that arrived are always used not to diverge.
+    pub fn take_inputs(&mut self, tick: u32) -> Option<Vec<KeyState>> {
+        if tick != self.base_tick || !self.connected.iter().any(|&c| c) {
+            return None;
+        }
+        let inputs = self.inputs.front()?;
+        let ready = inputs
+            .iter()
+            .zip(&self.connected)
+            .all(|(input, &connected)| input.is_some() || !connected);
+        if !ready {
+            return None;
+        }
+        let inputs = self.inputs.pop_front()?;
+        self.base_tick += 1;
+        Some(
+            inputs
+                .iter()
+                .map(|input| input.map_or_else(KeyState::new, KeyState::from_bits))
+                .collect(),
+        )
+    }
+
+    /// Next message to be sent to the relay
+    pub fn poll_outgoing(&mut self) -> Option<Vec<u8>> {
+        self.outgoing.pop_front()
+    }
+}
+
+/// Client of the authoritative server
+#[derive(Clone, Debug, Default)]
+pub struct Remote {
+    /// Last state received from the server
+    base: Vec<u8>,
+    /// Keys pressed on this machine
+    local_keys: KeyState,
+    /// Messages to be sent to the server
+    outgoing: VecDeque<Vec<u8>>,
+}
+
+impl Remote {
+    pub fn new() -> Self {
+        Remote::default()
+    }
+
+    /// Change the status of a local key, and queue it to be sent.
+    pub fn toggle_local_key(&mut self, key: Key, state: bool) {
+        let old = self.local_keys.to_bits();
+        self.local_keys.toggle(key, state);
+        let keys = self.local_keys.to_bits();
+        if keys != old {
+            // The server knows who we are, and applies it immediately.
+            let input = Message::Input {
+                tick: 0,
+                player: 0,
+                keys,
+            };
+            self.outgoing.push_back(input.encode());
+        }
+    }
+
+    /// Update the last state by `Message::State` or `Message::Delta`,
+    /// and return it as a snapshot.
+    pub fn receive_state(&mut self, msg: &Message) -> Result<&[u8], Error> {
+        match msg {
+            Message::State { data, .. } => self.base = data.clone(),
+            Message::Delta { data, .. } if !self.base.is_empty() => {
+                self.base = delta::apply(&self.base, data)?
+            }
+            _ => return Err(Error::InvalidMessage("no state to update".to_string())),
+        }
+        Ok(&self.base)
+    }
+
+    /// Next message to be sent to the server
+    pub fn poll_outgoing(&mut self) -> Option<Vec<u8>> {
+        self.outgoing.pop_front()
+    }
+}
@@ -0,0 +1,299 @@
+//! Rollback netcode
+//!
+//! Unlike the lockstep mode (`netplay`), the game never waits for
+//! remote inputs.  Missing inputs are predicted by repeating the last
+//! known `KeyState` of the player.  When the real input arrives late
+//! and differs from the prediction, the game is rewound to the
+//! snapshot of that tick and re-simulated up to the present.
+//!
+//! Messages are the same as the lockstep mode, so the same relay
+//! server can be used.
+
+use std::collections::{BTreeMap, VecDeque};
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::game_state::GameState;
+use crate::keyboard::KeyState;
+use crate::netplay::Message;
+
+/// Max ticks to run ahead of the confirmed inputs
+///
+/// `advance` stalls beyond this, and so does the cost of a rollback.
+pub const MAX_ROLLBACK_TICKS: u32 = 8;
+
+/// Rollback session on one peer
+#[wasm_bindgen]
+pub struct Rollback {
+    gs: GameState,
+    local_player: u32,
+    players: u32,
+    /// Inputs received (or entered locally) for each tick
+    confirmed: BTreeMap<u32, Vec<Option<KeyState>>>,
+    /// Inputs actually used to simulate each tick
+    used: BTreeMap<u32, Vec<KeyState>>,
+    /// Snapshots taken before simulating each tick
+    snapshots: BTreeMap<u32, Vec<u8>>,
+    /// Inputs of each player are confirmed before this tick
+    confirmed_until: Vec<u32>,
+    /// Players who have left the match
+    left: Vec<bool>,
+    /// The oldest tick simulated with a wrong prediction
+    rollback_from: Option<u32>,
+    /// Messages to be sent to the other peers
+    outgoing: VecDeque<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl Rollback {
+    /// Start rollback session of `gs` with `players` players.
+    #[wasm_bindgen(constructor)]
+    pub fn new(gs: GameState, local_player: u32, players: u32) -> Self {
+        let tick = gs.tick();
+        Rollback {
+            gs,
+            local_player,
+            players,
+            confirmed: BTreeMap::new(),
+            used: BTreeMap::new(),
+            snapshots: BTreeMap::new(),
+            confirmed_until: vec![tick; players as usize],
+            left: vec![false; players as usize],
+            rollback_from: None,
+            outgoing: VecDeque::new(),
+        }
+    }
+
+    /// Enter the keys pressed on this machine for the next tick.
+    ///
+    /// `keys` is in the format of `KeyState::to_bits`.  Only the first
+    /// call for each tick takes effect.
+    pub fn add_local_input(&mut self, keys: u8) {
+        let tick = self.gs.tick();
+        let player = self.local_player;
+        if self.input(tick, player).is_some() {
+            return;
+        }
+        self.add_input(tick, player, KeyState::from_bits(keys));
+        self.outgoing
+            .push_back(Message::Input { tick, player, keys }.encode());
+    }
+
+    /// Record the keys of a remote `player` at `tick`.
+    ///
+    /// If `tick` has been simulated with a wrong prediction, it will
+    /// be re-simulated on the next `advance`.
+    pub fn add_remote_input(&mut self, tick: u32, player: u32, keys: u8) {
+        if player >= self.players || player == self.local_player {
+            return;
+        }
+        if tick < self.confirmed_until[player as usize] || self.input(tick, player).is_some() {
+            return;
+        }
+        self.add_input(tick, player, KeyState::from_bits(keys));
+        self.check_predictions(tick, player);
+    }
+
+    /// Advance the game by one tick.
+    ///
+    /// If `add_local_input` has not been called for the tick, the
+    /// local keys are regarded as unchanged.  Returns false without
+    /// advancing if it has run too far ahead of the remote inputs.
+    ///
+    /// Events of the ticks re-simulated by a rollback are not reported
+    /// again, so those reported with the wrong prediction stand.
+    pub fn advance(&mut self) -> bool {
+        let tick = self.gs.tick();
+        if tick >= self.confirmed_tick() + MAX_ROLLBACK_TICKS {
+            return false;
+        }
+        if self.input(tick, self.local_player).is_none() {
+            let keys = self.input_or_prediction(tick, self.local_player);
+            self.add_local_input(keys.to_bits());
+        }
+        if let Some(from) = self.rollback_from.take() {
+            let gs = GameState::from_snapshot(&self.snapshots[&from])
+                .expect("snapshot taken by ourselves should be valid");
+            self.gs.replace(gs);
+            let events = self.gs.take_events();
+            for t in from..tick {
+                self.simulate(t);
+            }
+            self.gs.take_events();
+            for event in events {
+                self.gs.push_event(event);
+            }
+        }
+        self.simulate(tick);
+        self.discard_confirmed();
+        true
+    }
+
+    /// Callback function on receiving a message from the other peers
+    pub fn net_receive(&mut self, data: &[u8]) -> Result<(), Error> {
+        match Message::decode(data)? {
+            Message::Input { tick, player, keys } => self.add_remote_input(tick, player, keys),
+            Message::Leave { player } => self.leave(player),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Next message to be sent to the other peers if any
+    pub fn net_poll(&mut self) -> Option<Vec<u8>> {
+        self.outgoing.pop_front()
+    }
+
+    /// Draw the game at present
+    pub fn draw(&self) {
+        self.gs.draw();
+    }
+
+    /// Number of ticks simulated
+    pub fn tick(&self) -> u32 {
+        self.gs.tick()
+    }
+
+    /// Hash of the game at present
+    pub fn state_hash(&self) -> u32 {
+        self.gs.state_hash()
+    }
+}
+
+impl Rollback {
+    /// Game at present
+    pub fn game(&self) -> &GameState {
+        &self.gs
+    }
+
+    /// Remote `player` has left; regard the keys as released from the
+    /// first unconfirmed tick.
+    pub fn leave(&mut self, player: u32) {
+        if player >= self.players || player == self.local_player {
+            return;
+        }
+        self.left[player as usize] = true;
+        let from = self.confirmed_until[player as usize];
+        self.check_predictions(from, player);
+    }
+
+    /// The oldest tick whose inputs are not fully confirmed
+    fn confirmed_tick(&self) -> u32 {
+        (0..self.players)
+            .filter(|&p| p != self.local_player && !self.left[p as usize])
+            .map(|p| self.confirmed_until[p as usize])
+            .min()
+            .unwrap_or_else(|| self.gs.tick())
+    }
+
+    fn input(&self, tick: u32, player: u32) -> Option<&KeyState> {
+        self.confirmed.get(&tick)?[player as usize].as_ref()
+    }
+
+    fn add_input(&mut self, tick: u32, player: u32, keys: KeyState) {
+        let players = self.players as usize;
+        self.confirmed
+            .entry(tick)
+            .or_insert_with(|| vec![None; players])[player as usize] = Some(keys);
+
+        let until = &mut self.confirmed_until[player as usize];
+        while self
+            .confirmed
+            .get(until)
+            .is_some_and(|inputs| inputs[player as usize].is_some())
+        {
+            *until += 1;
+        }
+    }
+
+    /// Confirmed input of `player` at `tick`, or the prediction
+    fn input_or_prediction(&self, tick: u32, player: u32) -> KeyState {
+        if let Some(keys) = self.input(tick, player) {
+            return keys.clone();
+        }
+        if self.left[player as usize] {
+            return KeyState::new();
+        }
+        self.confirmed
+            .range(..tick)
+            .rev()
+            .find_map(|(_, inputs)| inputs[player as usize].clone())
+            .unwrap_or_default()
+    }
+
+    /// Schedule rollback if ticks from `tick` have been simulated with
+    /// inputs of `player` different from the present knowledge.
+    fn check_predictions(&mut self, tick: u32, player: u32) {
+        let wrong = self
+            .used
+            .range(tick..)
+            .find(|(&t, used)| used[player as usize] != self.input_or_prediction(t, player))
+            .map(|(&t, _)| t);
+        if let Some(t) = wrong {
+            self.rollback_from = Some(self.rollback_from.map_or(t, |from| from.min(t)));
+        }
+    }
+
+    /// Simulate `tick` taking the snapshot beforehand.
+    fn simulate(&mut self, tick: u32) {
+        self.snapshots.insert(tick, self.gs.snapshot());
+        let inputs: Vec<KeyState> = (0..self.players)
+            .map(|p| self.input_or_prediction(tick, p))
+            .collect();
+        self.gs.step_with(&inputs);
+        self.used.insert(tick, inputs);
+    }
+
+    /// Discard the history no longer needed for rollback.
+    ///
+    /// The inputs of the last confirmed tick are kept for prediction.
+    fn discard_confirmed(&mut self) {
+        let tick = self.confirmed_tick().min(self.gs.tick());
+        self.snapshots = self.snapshots.split_off(&tick);
+        self.used = self.used.split_off(&tick);
+        self.confirmed = self.confirmed.split_off(&tick.saturating_sub(1));
+    }
+}
+
+/// In-memory transport with simulated latency
+///
+/// Carries messages between peers in one process, e.g. for testing
+/// rollback sessions natively.
+pub struct Loopback {
+    /// Ticks for a message to arrive
+    latency: u32,
+    now: u32,
+    /// (arrival tick, destination peer, message)
+    queue: VecDeque<(u32, usize, Vec<u8>)>,
+}
+
+impl Loopback {
+    pub fn new(latency: u32) -> Self {
+        Loopback {
+            latency,
+            now: 0,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Send `data` to the peer `to`
+    pub fn send(&mut self, to: usize, data: Vec<u8>) {
+        self.queue.push_back((self.now + self.latency, to, data));
+    }
+
+    /// Let one tick pass
+    pub fn tick(&mut self) {
+        self.now += 1;
+    }
+
+    /// Messages arrived at the peer `to`
+    pub fn receive(&mut self, to: usize) -> Vec<Vec<u8>> {
+        let now = self.now;
+        let (arrived, rest) = self
+            .queue
+            .drain(..)
+            .partition(|&(at, dest, _)| at <= now && dest == to);
+        self.queue = rest;
+        arrived.into_iter().map(|(_, _, data)| data).collect()
+    }
+}
@@ -0,0 +1,139 @@
+//! Rules of a match
+
+use serde::{Deserialize, Serialize};
+
+/// Max number of players in a match
+pub const MAX_PLAYERS: u32 = 8;
+
+/// Time to show the end of a round before the next one in ms
+pub const ROUND_END_MS: i32 = 2000;
+
+/// How players come back after dying in a round
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Mode {
+    /// Dead players are out until the next round.
+    LastManStanding,
+    /// Players come back at their spawn cells until they lose all the
+    /// lives.
+    Lives(u32),
+    /// Players come back endlessly until the time is up, and the one
+    /// of the top score of kills minus suicides wins.
+    Deathmatch,
+}
+
+/// Settings of a match chosen before it starts
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rules {
+    /// Number of players including bots at the start of the match
+    ///
+    /// Players may join or leave later between rounds.  See
+    /// `GameState::join`.
+    pub players: u32,
+    /// Length of a round in seconds
+    pub round_secs: u32,
+    /// Rounds to win the match
+    pub wins_to_match: u32,
+    /// Walking speed in pixels per second of each speed level from 1
+    ///
+    /// Levels beyond the table stay at the last speed.
+    pub speed_table: Vec<u32>,
+    /// Team of each player by id for team battles
+    ///
+    /// Players beyond the list, or all of them if empty, are on their
+    /// own.  The round is won by the team of the last survivors.
+    pub teams: Vec<u32>,
+    /// Fire of a bomb hurts the teammates of its owner
+    pub friendly_fire: bool,
+    pub mode: Mode,
+    /// Players out of the round come back as ghosts riding the outer
+    /// wall, and a ghost killing a rival swaps in for it.
+    pub revenge: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            players: 4,
+            round_secs: 3 * 60,
+            wins_to_match: 3,
+            speed_table: vec![125, 155, 185, 215, 245, 275, 305, 335],
+            teams: vec![],
+            friendly_fire: true,
+            mode: Mode::LastManStanding,
+            revenge: false,
+        }
+    }
+}
+
+impl Rules {
+    /// Walking speed of `level` in pixels per second
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::rules::Rules;
+    ///
+    /// let rules = Rules {
+    ///     speed_table: vec![100, 150],
+    ///     ..Rules::default()
+    /// };
+    /// assert_eq!(rules.speed(1), 100);
+    /// assert_eq!(rules.speed(2), 150);
+    /// assert_eq!(rules.speed(9), 150);
+    /// ```
+    pub fn speed(&self, level: u8) -> u32 {
+        let i = (level.max(1) as usize - 1).min(self.speed_table.len().saturating_sub(1));
+        self.speed_table.get(i).copied().unwrap_or(0)
+    }
+
+    /// Lives of each player at the start of a round
+    ///
+    /// Lives are never lost in `Mode::Deathmatch`.
+    pub fn lives(&self) -> u32 {
+        match self.mode {
+            Mode::LastManStanding | Mode::Deathmatch => 1,
+            Mode::Lives(lives) => lives.max(1),
+        }
+    }
+
+    /// Players split into `count` teams by turns, or on their own if
+    /// `count` is less than 2
+    pub fn set_teams(&mut self, count: u32) {
+        self.teams = if count < 2 {
+            vec![]
+        } else {
+            (0..MAX_PLAYERS).map(|p| p % count).collect()
+        };
+    }
+
+    /// Team of `player` if in a team battle
+    pub fn team(&self, player: u32) -> Option<u32> {
+        self.teams.get(player as usize).copied()
+    }
+
+    /// Predicate `a` and `b` are different players of the same team
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bomberhuman::rules::Rules;
+    ///
+    /// let mut rules = Rules::default();
+    /// rules.set_teams(2);
+    /// assert!(rules.teammates(0, 2));
+    /// assert!(!rules.teammates(0, 1));
+    /// assert!(!rules.teammates(0, 0));
+    ///
+    /// rules.friendly_fire = false;
+    /// assert!(!rules.hurts(0, 2));
+    /// assert!(rules.hurts(0, 0));
+    /// ```
+    pub fn teammates(&self, a: u32, b: u32) -> bool {
+        a != b && self.team(a).is_some() && self.team(a) == self.team(b)
+    }
+
+    /// Predicate fire of the bomb of `owner` hurts `player`
+    pub fn hurts(&self, owner: u32, player: u32) -> bool {
+        self.friendly_fire || !self.teammates(owner, player)
+    }
+}
@@ -0,0 +1,513 @@
+//! Scenes of the game from the title to the results
+//!
+//! `App` runs the whole game in the browser: the title, the choice of
+//! players and bots, the rules, the match, the pause menu and the
+//! results.  Every scene is driven by `Key` inputs of any bind, so the
+//! game can be played with gamepads only.
+//!
+//! ```text
+//! Title → PlayerSelect ⇄ Settings → Match ⇄ Pause
+//!   ↑                                 ↓       │
+//!   ├──────────── Results ←───────────┘       │
+//!   └──────────────── QUIT ───────────────────┘
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+use crate::actors::ActorId;
+use crate::atlas::{AnimState, Animator, Atlas};
+use crate::bot;
+use crate::error::Error;
+use crate::events::GameEvent;
+use crate::game_state::{GameState, RoundEnd};
+use crate::geometry::cells_in;
+use crate::hud::{GLYPH_WIDTH, HUD_HEIGHT};
+use crate::keyboard::{Key, KeyState};
+use crate::rules::{Mode, Rules, MAX_PLAYERS, ROUND_END_MS};
+use crate::screen::*;
+
+/// Range and step of `Rules::round_secs` in the settings
+const ROUND_SECS: (u32, u32, u32) = (60, 300, 30);
+
+/// Max of `Rules::wins_to_match` in the settings
+const MAX_WINS: u32 = 9;
+
+/// Max of `Mode::Lives` in the settings
+const MAX_LIVES: u32 = 9;
+
+/// Choices of the number of teams in the settings, 1 for no teams
+const TEAM_COUNTS: [u32; 3] = [1, 2, 4];
+
+/// Height of a line of menus
+const LINE_HEIGHT: i32 = 60;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scene {
+    Title,
+    /// Number of humans and bots
+    PlayerSelect,
+    /// Rules of the match
+    Settings,
+    Match,
+    Pause,
+    Results,
+}
+
+/// Game application switching scenes
+#[wasm_bindgen]
+pub struct App {
+    width: u32,
+    height: u32,
+    scene: Scene,
+    /// Selected item of the menu
+    cursor: usize,
+    /// Players controlled by binds 0, 1, ...
+    humans: u32,
+    /// Players controlled by `bot` after the humans
+    bots: u32,
+    /// Players controlled by binds in the match, and the others by `bot`
+    human: Vec<bool>,
+    rules: Rules,
+    seed: u32,
+    gs: GameState,
+    /// Keys of each bind with the edges since the last update
+    keys: Vec<KeyState>,
+    /// Time left to show the end of the round, and its winner
+    round_end: Option<(i32, Option<u32>)>,
+    /// Events of the match not yet drained by the front end
+    events: RefCell<Vec<GameEvent>>,
+    draw_list: RefCell<DrawList>,
+}
+
+#[wasm_bindgen]
+impl App {
+    pub fn new(width: u32, height: u32) -> Self {
+        App::with_seed(width, height, rand::random())
+    }
+
+    /// Create app whose matches are reproducible by `seed`.
+    pub fn with_seed(width: u32, height: u32, seed: u32) -> Self {
+        let rules = Rules {
+            players: 2,
+            ..Rules::default()
+        };
+        App {
+            width,
+            height,
+            scene: Scene::Title,
+            cursor: 0,
+            humans: 1,
+            bots: 1,
+            human: vec![false; MAX_PLAYERS as usize],
+            gs: GameState::with_rules(width, height, seed, rules.clone()),
+            rules,
+            seed,
+            keys: vec![KeyState::new(); MAX_PLAYERS as usize],
+            round_end: None,
+            events: RefCell::new(vec![]),
+            draw_list: RefCell::new(DrawList::new()),
+        }
+    }
+
+    /// Update the current scene by `delta` ms.
+    pub fn update(&mut self, delta: i32) {
+        match self.scene {
+            Scene::Title => self.update_title(),
+            Scene::PlayerSelect => self.update_player_select(),
+            Scene::Settings => self.update_settings(),
+            Scene::Match => self.update_match(delta),
+            Scene::Pause => self.update_pause(),
+            Scene::Results => self.update_results(),
+        }
+        for ks in &mut self.keys {
+            ks.next_tick();
+        }
+    }
+
+    /// Draw the current scene.
+    pub fn draw(&self) {
+        let mut list = self.draw_list.borrow_mut();
+        list.clear();
+        self.draw_into(&mut list);
+        screen_flush(list.as_slice());
+    }
+
+    /// Callback function on change the key-input status
+    pub fn toggle_key(&mut self, bind: u32, key: Key, state: bool) -> Result<(), Error> {
+        let ks = self
+            .keys
+            .get_mut(bind as usize)
+            .ok_or(Error::NoSuchPlayer(bind))?;
+        ks.toggle(key, state);
+        Ok(())
+    }
+
+    /// See `GameState::set_axis`.
+    pub fn set_axis(&mut self, bind: u32, x: f32, y: f32) -> Result<(), Error> {
+        let ks = self
+            .keys
+            .get_mut(bind as usize)
+            .ok_or(Error::NoSuchPlayer(bind))?;
+        ks.set_axis(x, y);
+        Ok(())
+    }
+
+    /// Let `bind` play the player of the same id from the next round,
+    /// or take it over from the bot at once.  See `GameState::join`.
+    pub fn join(&mut self, bind: u32) -> Result<(), Error> {
+        self.gs.join(bind)?;
+        self.human[bind as usize] = true;
+        Ok(())
+    }
+
+    /// Let the player of `bind` leave after the current round, played
+    /// by the bot until then.  See `GameState::leave`.
+    pub fn leave(&mut self, bind: u32) -> Result<(), Error> {
+        self.gs.leave(bind)?;
+        self.human[bind as usize] = false;
+        Ok(())
+    }
+
+    /// Take the events of the match happened since the last call.
+    /// See `GameState::drain_events`.
+    pub fn drain_events(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.take_events()).map_err(Into::into)
+    }
+
+    /// Time left in the round in ms
+    pub fn time_left(&self) -> u32 {
+        self.gs.time_left()
+    }
+
+    /// Use the sprite atlas descriptor `json`.
+    /// See `GameState::load_atlas`.
+    pub fn load_atlas(&self, json: &str) -> Result<(), Error> {
+        let atlas = Atlas::from_json(json)?;
+        self.draw_list.borrow_mut().set_atlas(Rc::new(atlas));
+        Ok(())
+    }
+
+    pub fn scene(&self) -> Scene {
+        self.scene
+    }
+
+    /// Advance the match by one tick while paused.
+    /// See `GameState::step_tick`.
+    pub fn step_tick(&mut self) -> bool {
+        self.scene == Scene::Pause && self.gs.step_tick()
+    }
+
+    /// See `GameState::set_time_scale`.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.gs.set_time_scale(scale);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.gs.time_scale()
+    }
+}
+
+impl App {
+    /// The game of the current or the last match
+    pub fn game(&self) -> &GameState {
+        &self.gs
+    }
+
+    /// Take the events of the match happened since the last call
+    pub fn take_events(&self) -> Vec<GameEvent> {
+        self.events.replace(vec![])
+    }
+
+    /// Sprites to draw the current scene
+    pub fn draw_list(&self) -> DrawList {
+        let mut list = DrawList::with_atlas(self.draw_list.borrow().atlas());
+        self.draw_into(&mut list);
+        list
+    }
+
+    /// Add the sprites of the current scene to `list`.
+    pub fn draw_into(&self, list: &mut DrawList) {
+        let lines: Vec<String> = match self.scene {
+            Scene::Title => vec!["BOMBERHUMAN".into(), String::new(), "PRESS START".into()],
+            Scene::PlayerSelect => self.menu(&[
+                format!("HUMANS <{}>", self.humans),
+                format!("BOTS   <{}>", self.bots),
+                "NEXT".into(),
+            ]),
+            Scene::Settings => {
+                let secs = self.rules.round_secs;
+                let teams = match team_count(&self.rules) {
+                    1 => "OFF".to_string(),
+                    n => n.to_string(),
+                };
+                let mode = match self.rules.mode {
+                    Mode::LastManStanding => "LAST MAN".to_string(),
+                    Mode::Lives(n) => format!("LIVES {}", n),
+                    Mode::Deathmatch => "DEATHMATCH".to_string(),
+                };
+                let on_off = |on: bool| if on { "ON" } else { "OFF" };
+                self.menu(&[
+                    format!("TIME <{}:{:02}>", secs / 60, secs % 60),
+                    format!("WINS <{}>", self.rules.wins_to_match),
+                    format!("MODE <{}>", mode),
+                    format!("TEAMS <{}>", teams),
+                    format!("FRIENDLY FIRE <{}>", on_off(self.rules.friendly_fire)),
+                    format!("REVENGE <{}>", on_off(self.rules.revenge)),
+                    "START".into(),
+                    "BACK".into(),
+                ])
+            }
+            Scene::Match => match self.round_end {
+                Some((_, Some(winner))) => vec![format!("{} WINS!", self.winner_name(winner))],
+                Some((_, None)) => vec!["DRAW".into()],
+                None => vec![],
+            },
+            Scene::Pause => {
+                let mut lines = vec!["PAUSE".into(), String::new()];
+                lines.extend(self.menu(&["RESUME".into(), "QUIT".into()]));
+                lines
+            }
+            Scene::Results => {
+                let mut lines = match self.gs.match_winner() {
+                    Some(w) => vec![format!("{} WINS THE MATCH!", self.winner_name(w))],
+                    None => vec!["NO CONTEST".into()],
+                };
+                lines.push(String::new());
+                for p in 0..MAX_PLAYERS {
+                    if self.gs.seated(p) || self.gs.wins(p) > 0 {
+                        lines.push(format!("P{} {}", p + 1, self.gs.wins(p)));
+                    }
+                }
+                lines
+            }
+        };
+
+        match self.scene {
+            Scene::Match | Scene::Pause => self.gs.draw_into(list),
+            _ => {
+                let floor = Animator::new(ActorId::Floor, AnimState::Idle);
+                let height = self.height as i32 + HUD_HEIGHT;
+                for cell in cells_in(self.width as i32, height) {
+                    let (x, y) = cell.to_pixel().to_tuple();
+                    list.put_animation(Layer::Floor, x, y, &floor);
+                }
+            }
+        }
+
+        let height = self.height as i32 + HUD_HEIGHT;
+        let top = (height - lines.len() as i32 * LINE_HEIGHT) / 2;
+        for (i, line) in lines.iter().enumerate() {
+            let x = (self.width as i32 - line.len() as i32 * GLYPH_WIDTH) / 2;
+            let y = top + i as i32 * LINE_HEIGHT;
+            list.put_text(Layer::Hud, x, y, line, GLYPH_WIDTH);
+        }
+        list.sort();
+    }
+
+    /// `PLAYER n` of `player`, or `TEAM n` of it in team battles
+    fn winner_name(&self, player: u32) -> String {
+        match self.gs.rules().team(player) {
+            Some(team) => format!("TEAM {}", team + 1),
+            None => format!("PLAYER {}", player + 1),
+        }
+    }
+
+    /// Lines of the menu with `items` pointing the one at the cursor
+    fn menu(&self, items: &[String]) -> Vec<String> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let mark = if i == self.cursor { "- " } else { "  " };
+                format!("{}{}", mark, item)
+            })
+            .collect()
+    }
+
+    /// Predicate `key` got pressed by any bind since the last update
+    fn pressed(&self, key: Key) -> bool {
+        self.keys.iter().any(|ks| ks.pressed(key))
+    }
+
+    /// Move the cursor by Up and Down in the menu of `len` items, and
+    /// return -1 or 1 by Left or Right to change the item.
+    fn navigate(&mut self, len: usize) -> i32 {
+        if self.pressed(Key::Up) {
+            self.cursor = (self.cursor + len - 1) % len;
+        }
+        if self.pressed(Key::Down) {
+            self.cursor = (self.cursor + 1) % len;
+        }
+        match (self.pressed(Key::Left), self.pressed(Key::Right)) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        }
+    }
+
+    fn confirmed(&self) -> bool {
+        self.pressed(Key::Button1) || self.pressed(Key::Start)
+    }
+
+    fn goto(&mut self, scene: Scene) {
+        self.scene = scene;
+        self.cursor = 0;
+    }
+
+    fn update_title(&mut self) {
+        if self.confirmed() {
+            self.goto(Scene::PlayerSelect);
+        }
+    }
+
+    fn update_player_select(&mut self) {
+        let change = self.navigate(3);
+        match self.cursor {
+            0 => {
+                self.humans = add_within(self.humans, change, 1, MAX_PLAYERS);
+                self.bots = self.bots.min(MAX_PLAYERS - self.humans);
+            }
+            1 => self.bots = add_within(self.bots, change, 0, MAX_PLAYERS - self.humans),
+            _ => {
+                if self.confirmed() && self.humans + self.bots >= 2 {
+                    self.rules.players = self.humans + self.bots;
+                    self.goto(Scene::Settings);
+                }
+            }
+        }
+    }
+
+    fn update_settings(&mut self) {
+        let change = self.navigate(8);
+        let (min, max, step) = ROUND_SECS;
+        match self.cursor {
+            0 => {
+                let secs = self.rules.round_secs;
+                self.rules.round_secs = add_within(secs, change * step as i32, min, max);
+            }
+            1 => {
+                self.rules.wins_to_match = add_within(self.rules.wins_to_match, change, 1, MAX_WINS)
+            }
+            2 => {
+                let modes = modes();
+                let i = modes
+                    .iter()
+                    .position(|&m| m == self.rules.mode)
+                    .unwrap_or(0);
+                let i = add_within(i as u32, change, 0, modes.len() as u32 - 1);
+                self.rules.mode = modes[i as usize];
+            }
+            3 => {
+                let count = team_count(&self.rules);
+                let i = TEAM_COUNTS.iter().position(|&n| n == count).unwrap_or(0);
+                let i = add_within(i as u32, change, 0, TEAM_COUNTS.len() as u32 - 1);
+                self.rules.set_teams(TEAM_COUNTS[i as usize]);
+            }
+            4 => {
+                if change != 0 {
+                    self.rules.friendly_fire = !self.rules.friendly_fire;
+                }
+            }
+            5 => {
+                if change != 0 {
+                    self.rules.revenge = !self.rules.revenge;
+                }
+            }
+            6 => {
+                if self.confirmed() {
+                    self.start_match();
+                }
+            }
+            _ => {
+                if self.confirmed() {
+                    self.goto(Scene::PlayerSelect);
+                }
+            }
+        }
+    }
+
+    fn start_match(&mut self) {
+        self.seed = self.seed.wrapping_add(1);
+        let scale = self.gs.time_scale();
+        self.gs = GameState::with_rules(self.width, self.height, self.seed, self.rules.clone());
+        self.gs.set_time_scale(scale);
+        self.human = (0..MAX_PLAYERS).map(|p| p < self.humans).collect();
+        self.round_end = None;
+        self.events.borrow_mut().clear();
+        self.goto(Scene::Match);
+    }
+
+    fn update_match(&mut self, delta: i32) {
+        if self.pressed(Key::Start) {
+            self.gs.set_paused(true);
+            self.goto(Scene::Pause);
+            return;
+        }
+
+        let ids: Vec<u32> = self.gs.players().iter().map(|p| p.id).collect();
+        for p in ids {
+            let keys = if self.human[p as usize] {
+                let mut keys = self.keys[p as usize].clone();
+                keys.start = false;
+                keys
+            } else {
+                bot::think(&self.gs, p)
+            };
+            self.gs.set_keys(p, keys);
+        }
+        self.gs.update(delta);
+
+        for event in self.gs.take_events() {
+            if let GameEvent::RoundOver { winner } = event {
+                self.round_end = Some((ROUND_END_MS, winner));
+            }
+            self.events.borrow_mut().push(event);
+        }
+
+        if let Some((wait, winner)) = self.round_end {
+            match self.gs.round_end_step(wait, delta) {
+                RoundEnd::Showing(wait) => self.round_end = Some((wait, winner)),
+                RoundEnd::NextRound => self.round_end = None,
+                RoundEnd::MatchOver(_) => self.goto(Scene::Results),
+            }
+        }
+    }
+
+    fn update_pause(&mut self) {
+        self.navigate(2);
+        if self.pressed(Key::Start) || (self.cursor == 0 && self.confirmed()) {
+            self.gs.set_paused(false);
+            self.scene = Scene::Match;
+        } else if self.cursor == 1 && self.confirmed() {
+            self.goto(Scene::Title);
+        }
+    }
+
+    fn update_results(&mut self) {
+        if self.confirmed() {
+            self.goto(Scene::Title);
+        }
+    }
+}
+
+/// `value` + `change` kept within `min`..=`max`
+fn add_within(value: u32, change: i32, min: u32, max: u32) -> u32 {
+    ((value as i32 + change).max(min as i32) as u32).min(max)
+}
+
+/// Choices of `Rules::mode` in the settings
+fn modes() -> Vec<Mode> {
+    let lives = (2..=MAX_LIVES).map(Mode::Lives);
+    std::iter::once(Mode::LastManStanding)
+        .chain(lives)
+        .chain(std::iter::once(Mode::Deathmatch))
+        .collect()
+}
+
+/// Number of teams of `rules`, 1 for no teams
+fn team_count(rules: &Rules) -> u32 {
+    rules.teams.iter().max().map_or(1, |&t| t + 1)
+}
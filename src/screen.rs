@@ -1,9 +1,244 @@
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
-use crate::actors::ActorId;
+use crate::atlas::{Animator, Atlas};
 
 #[wasm_bindgen(module = "/src/javascripts/screen.js")]
 extern "C" {
-    pub fn screen_put_sprite(x: i32, y: i32, actor_id: ActorId, action: u32);
-    pub fn screen_clear_rect(x: i32, y: i32, width: u32, height: u32);
+    /// Clear the screen and draw all the commands in `cmds` made by `DrawList`
+    pub fn screen_flush(cmds: &[i32]);
+}
+
+/// Number of `i32`s in a packed `DrawCommand`
+pub const DRAW_COMMAND_LEN: usize = 5;
+
+/// Sprite to be put at (`x`, `y`)
+///
+/// The image is at `row`, `col` of the sprite sheet, drawn with the
+/// colors of `palette`.  See `Atlas::palette`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawCommand {
+    pub x: i32,
+    pub y: i32,
+    pub row: i32,
+    pub col: i32,
+    pub palette: i32,
+}
+
+/// Render layers from the bottom to the top
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Floor,
+    Items,
+    Bombs,
+    Blocks,
+    /// Sorted by y, so that the one in front covers the one behind
+    Characters,
+    Effects,
+    Hud,
+}
+
+thread_local! {
+    static DEFAULT_ATLAS: Rc<Atlas> = Rc::new(Atlas::default());
+}
+
+/// Sprites to be drawn in a frame
+///
+/// Commands are packed as `[x, y, row, col, palette, x, y, ...]`, so
+/// that JS can read them as an `Int32Array` in one `screen_flush` call.
+/// `sort` puts them in the order of `Layer`.
+///
+/// # Examples
+///
+/// ```
+/// use bomberhuman::actors::ActorId;
+/// use bomberhuman::atlas::{AnimState, Animator};
+/// use bomberhuman::screen::{DrawList, Layer};
+///
+/// let mut list = DrawList::new();
+/// list.put_sprite(Layer::Effects, 60, 120, 4, 3);
+/// let block = Animator::new(ActorId::Block, AnimState::Soft);
+/// list.put_animation(Layer::Blocks, 0, 0, &block);
+/// list.sort();
+/// assert_eq!(list.as_slice(), &[0, 0, 5, 1, 0, 60, 120, 4, 3, 0]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DrawList {
+    cmds: Vec<i32>,
+    /// Layer and y of each command to sort by
+    keys: Vec<(Layer, i32)>,
+    /// Keys with the indices of the commands, reused by `sort` not to
+    /// allocate in every frame
+    order: Vec<(Layer, i32, usize)>,
+    /// Commands in the sorted order, swapped with `cmds` by `sort`
+    sorted: Vec<i32>,
+    /// Added to the positions of commands
+    origin: (i32, i32),
+    /// Layout of the sprite sheet
+    atlas: Rc<Atlas>,
+}
+
+impl Default for DrawList {
+    fn default() -> Self {
+        DrawList::with_atlas(DEFAULT_ATLAS.with(Rc::clone))
+    }
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        DrawList::default()
+    }
+
+    pub fn with_atlas(atlas: Rc<Atlas>) -> Self {
+        DrawList {
+            cmds: vec![],
+            keys: vec![],
+            order: vec![],
+            sorted: vec![],
+            origin: (0, 0),
+            atlas,
+        }
+    }
+
+    pub fn atlas(&self) -> Rc<Atlas> {
+        Rc::clone(&self.atlas)
+    }
+
+    pub fn set_atlas(&mut self, atlas: Rc<Atlas>) {
+        self.atlas = atlas;
+    }
+
+    /// Put the following commands relative to (`x`, `y`)
+    pub fn set_origin(&mut self, x: i32, y: i32) {
+        self.origin = (x, y);
+    }
+
+    /// Remove all the commands keeping the memory
+    pub fn clear(&mut self) {
+        self.cmds.clear();
+        self.keys.clear();
+    }
+
+    /// Put the sprite at `row`, `col` of the sprite sheet at (`x`, `y`)
+    /// on `layer`
+    pub fn put_sprite(&mut self, layer: Layer, x: i32, y: i32, row: u32, col: u32) {
+        self.put_sprite_in(layer, x, y, row, col, 0);
+    }
+
+    /// Put the sprite as `put_sprite` with the colors of `palette`
+    pub fn put_sprite_in(
+        &mut self,
+        layer: Layer,
+        x: i32,
+        y: i32,
+        row: u32,
+        col: u32,
+        palette: u32,
+    ) {
+        self.push(
+            layer,
+            DrawCommand {
+                x,
+                y,
+                row: row as i32,
+                col: col as i32,
+                palette: palette as i32,
+            },
+        );
+    }
+
+    /// Put the current frame of `animator` at (`x`, `y`) on `layer`
+    ///
+    /// Nothing is drawn if the atlas has no such animation.
+    pub fn put_animation(&mut self, layer: Layer, x: i32, y: i32, animator: &Animator) {
+        if let Some((row, col)) = self.atlas.frame(animator) {
+            let palette = self.atlas.palette(animator.actor());
+            self.put_sprite_in(layer, x, y, row, col, palette);
+        }
+    }
+
+    /// Put the current frame of `animator` as `put_animation` with the
+    /// colors of `palette` instead of the ones of the atlas
+    pub fn put_animation_in(
+        &mut self,
+        layer: Layer,
+        x: i32,
+        y: i32,
+        animator: &Animator,
+        palette: u32,
+    ) {
+        if let Some((row, col)) = self.atlas.frame(animator) {
+            self.put_sprite_in(layer, x, y, row, col, palette);
+        }
+    }
+
+    /// Put `text` from (`x`, `y`) with the font of the atlas moving
+    /// `advance` for each character.  Characters without glyphs are
+    /// left blank.
+    pub fn put_text(&mut self, layer: Layer, x: i32, y: i32, text: &str, advance: i32) {
+        for (i, c) in text.chars().enumerate() {
+            if let Some((row, col)) = self.atlas.glyph(c) {
+                self.put_sprite(layer, x + i as i32 * advance, y, row, col);
+            }
+        }
+    }
+
+    pub fn push(&mut self, layer: Layer, mut cmd: DrawCommand) {
+        cmd.x += self.origin.0;
+        cmd.y += self.origin.1;
+        self.cmds
+            .extend_from_slice(&[cmd.x, cmd.y, cmd.row, cmd.col, cmd.palette]);
+        let y = if layer == Layer::Characters { cmd.y } else { 0 };
+        self.keys.push((layer, y));
+    }
+
+    /// Sort the commands from the bottom layer, and from the back in
+    /// `Layer::Characters`.  Others keep the order they are put.
+    pub fn sort(&mut self) {
+        self.order.clear();
+        let keys = self.keys.iter().enumerate();
+        self.order
+            .extend(keys.map(|(i, &(layer, y))| (layer, y, i)));
+        // The indices keep the order of equal keys without the buffer
+        // of a stable sort.
+        self.order.sort_unstable();
+        if self.order.iter().enumerate().all(|(i, o)| i == o.2) {
+            return;
+        }
+        self.sorted.clear();
+        self.keys.clear();
+        for &(layer, y, i) in &self.order {
+            let start = i * DRAW_COMMAND_LEN;
+            self.sorted
+                .extend_from_slice(&self.cmds[start..start + DRAW_COMMAND_LEN]);
+            self.keys.push((layer, y));
+        }
+        std::mem::swap(&mut self.cmds, &mut self.sorted);
+    }
+
+    /// Number of commands
+    pub fn len(&self) -> usize {
+        self.cmds.len() / DRAW_COMMAND_LEN
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cmds.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = DrawCommand> + '_ {
+        self.cmds
+            .chunks_exact(DRAW_COMMAND_LEN)
+            .map(|c| DrawCommand {
+                x: c[0],
+                y: c[1],
+                row: c[2],
+                col: c[3],
+                palette: c[4],
+            })
+    }
+
+    /// Packed commands
+    pub fn as_slice(&self) -> &[i32] {
+        &self.cmds
+    }
 }
@@ -2,9 +2,10 @@ use crate::actors::block::*;
 use crate::actors::player::*;
 use crate::actors::power::*;
 use crate::geometry::*;
+use crate::rules::MAX_PLAYERS;
+use rand::Rng;
 
 /// Stage
-
 pub struct Stage {
     pub blocks: Vec<Block>,
     pub players: Vec<Player>,
@@ -13,57 +14,98 @@ pub struct Stage {
     pub height: usize,
 }
 
+/// Size of the stage in cells
+const STAGE_COLS: i32 = 15;
+const STAGE_ROWS: i32 = 13;
+
 /// Base information to creat stage
 ///
+/// Players start at the corners, and the fifth and later players at
+/// the middles of the edges.  The cells next to them are left open
+/// (○ only when the player at the middle takes part).
+///
 /// ```text
 ///  0 1 2 3 4 5 6 7 8 9 A B C D E
 /// 0■■■■■■■■■■■■■■■
-/// 1■××□□□□□□□□□××■
-/// 2■×■□■□■□■□■□■×■
+/// 1■××□□□○○○□□□××■
+/// 2■×■□■□■○■□■□■×■
 /// 3■□□□□□□□□□□□□□■
 /// 4■□■□■□■□■□■□■□■
-/// 5■□□□□□□□□□□□□□■
-/// 6■□■□■□■□■□■□■□■
-/// 7■□□□□□□□□□□□□□■
+/// 5■○□□□□□□□□□□□○■
+/// 6■○■□■□■□■□■□■○■
+/// 7■○□□□□□□□□□□□○■
 /// 8■□■□■□■□■□■□■□■
 /// 9■□□□□□□□□□□□□□■
-/// A■×■□■□■□■□■□■×■
-/// B■××□□□□□□□□□××■
+/// A■×■□■□■○■□■□■×■
+/// B■××□□□○○○□□□××■
 /// C■■■■■■■■■■■■■■■
 /// ```
-const BLOCK_MAP: [u8; 195] = [
+///
+/// 0: soft block or none, 1: hard block, 2: none, n = 3 to 10: player
+/// of id n − 3, n ≥ 11: none if the player of id n − 11 takes part,
+/// otherwise as 0
+///
+/// The cell of a fifth or later player not taking part is also as 0.
+const BLOCK_MAP: [u8; (STAGE_COLS * STAGE_ROWS) as usize] = [
     // 1  2  3  4  5  6  7  8  9  A  B  C  D  E
     1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, // 0
-    1, 4, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 5, 1, // 1
-    1, 2, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 1, // 2
+    1, 4, 2, 0, 0, 0, 15, 7, 15, 0, 0, 0, 2, 5, 1, // 1
+    1, 2, 1, 0, 1, 0, 1, 15, 1, 0, 1, 0, 1, 2, 1, // 2
     1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // 3
     1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, // 4
-    1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // 5
-    1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, // 6
-    1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // 7
+    1, 17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 18, 1, // 5
+    1, 9, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 10, 1, // 6
+    1, 17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 18, 1, // 7
     1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, // 8
     1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // 9
-    1, 2, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 1, // A
-    1, 6, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1, // B
+    1, 2, 1, 0, 1, 0, 1, 16, 1, 0, 1, 0, 1, 2, 1, // A
+    1, 6, 2, 0, 0, 0, 16, 8, 16, 0, 0, 0, 2, 3, 1, // B
     1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, // C
 ];
 
+/// Outer wall of `BLOCK_MAP` where ghosts ride in `Rules::revenge`
+pub const BORDER: Border = Border {
+    min: CellPos { col: 0, row: 0 },
+    max: CellPos {
+        col: STAGE_COLS - 1,
+        row: STAGE_ROWS - 1,
+    },
+};
+
+/// Ring of cells on the edges of the rectangle from `min` to `max`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Border {
+    /// Top-left corner
+    pub min: CellPos,
+    /// Bottom-right corner
+    pub max: CellPos,
+}
+
 impl Stage {
-    pub fn new() -> Stage {
+    /// Create stage placing soft blocks and items by `rng`
+    ///
+    /// Only players of ids whose `seats` are true start on it.
+    pub fn new<R: Rng>(rng: &mut R, seats: &[bool]) -> Stage {
+        let seated = |id: u8| seats.get(id as usize).copied().unwrap_or(false);
         let mut blocks: Vec<Block> = vec![];
         let mut players: Vec<Player> = vec![];
         let mut powers: Vec<Power> = vec![];
         for (i, info) in BLOCK_MAP.iter().enumerate() {
-            let (x, y) = (((i % 15) as i32) * GS, ((i / 15) as i32) * GS);
+            let cell = cell!(i as i32 % STAGE_COLS, i as i32 / STAGE_COLS);
+            let (x, y) = cell.to_pixel().to_tuple();
             match info {
                 1 => blocks.push(Block::hard(x, y)),
                 2 => (),
-                id @ 3..=6 => players.push(Player::new(*id as u32 - 3, x, y)),
+                &id @ 3..=10 if seated(id - 3) => players.push(
+                    Player::new(id as u32 - 3, x, y).expect("BLOCK_MAP should have valid ids"),
+                ),
+                3..=6 => (),
+                &id @ 11.. if seated(id - 11) => (),
                 _ => {
-                    if rand::random() {
+                    if rng.gen() {
                         blocks.push(Block::soft(x, y));
-                        if rand::random() {
-                            powers.push(Power::random_item(x, y));
+                        if rng.gen() {
+                            powers.push(Power::random_item(x, y, rng));
                         }
                     }
                 }
@@ -71,8 +113,8 @@ impl Stage {
         }
 
         Stage {
-            width: 15,
-            height: 13,
+            width: STAGE_COLS as usize,
+            height: STAGE_ROWS as usize,
             blocks,
             players,
             powers,
@@ -80,8 +122,134 @@ impl Stage {
     }
 }
 
+impl Border {
+    /// Cell of the ring nearest to `cell`
+    pub fn nearest(&self, cell: CellPos) -> CellPos {
+        let col = cell.col.clamp(self.min.col, self.max.col);
+        let row = cell.row.clamp(self.min.row, self.max.row);
+        let edges = [
+            (col - self.min.col, cell!(self.min.col, row)),
+            (self.max.col - col, cell!(self.max.col, row)),
+            (row - self.min.row, cell!(col, self.min.row)),
+            (self.max.row - row, cell!(col, self.max.row)),
+        ];
+        let (_, nearest) = edges.iter().min_by_key(|(d, _)| *d).unwrap();
+        *nearest
+    }
+
+    /// Direction into the inside from `cell` on an edge, or None at
+    /// the corners and off the ring
+    pub fn inward(&self, cell: CellPos) -> Option<Direction> {
+        let on_col = cell.col == self.min.col || cell.col == self.max.col;
+        let on_row = cell.row == self.min.row || cell.row == self.max.row;
+        match (on_col, on_row) {
+            (true, true) | (false, false) => None,
+            (false, true) if cell.row == self.min.row => Some(Direction::S),
+            (false, true) => Some(Direction::N),
+            (true, false) if cell.col == self.min.col => Some(Direction::E),
+            (true, false) => Some(Direction::W),
+        }
+    }
+
+    /// Steps along the ring from `a` to `b` the shorter way
+    pub fn distance(&self, a: CellPos, b: CellPos) -> i32 {
+        let d = (self.position(b) - self.position(a)).rem_euclid(self.perimeter());
+        d.min(self.perimeter() - d)
+    }
+
+    /// Next cell along the ring from `from` the shorter way to `to`, or
+    /// `from` itself if it is `to`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate bomberhuman; fn main() {
+    /// use bomberhuman::stage::BORDER;
+    ///
+    /// assert_eq!(BORDER.next_toward(cell!(1, 0), cell!(0, 5)), cell!(0, 0));
+    /// assert_eq!(BORDER.next_toward(cell!(14, 0), cell!(14, 12)), cell!(14, 1));
+    /// # }
+    /// ```
+    pub fn next_toward(&self, from: CellPos, to: CellPos) -> CellPos {
+        let (a, b) = (self.position(from), self.position(to));
+        let d = (b - a).rem_euclid(self.perimeter());
+        match d {
+            0 => from,
+            d if d <= self.perimeter() / 2 => self.cell_at(a + 1),
+            _ => self.cell_at(a - 1),
+        }
+    }
+
+    /// Cells around the ring
+    fn perimeter(&self) -> i32 {
+        2 * (self.max.col - self.min.col + self.max.row - self.min.row)
+    }
+
+    /// Steps from the top-left corner clockwise to `cell` on the ring
+    fn position(&self, cell: CellPos) -> i32 {
+        let (w, h) = (self.max.col - self.min.col, self.max.row - self.min.row);
+        if cell.row == self.min.row {
+            cell.col - self.min.col
+        } else if cell.col == self.max.col {
+            w + cell.row - self.min.row
+        } else if cell.row == self.max.row {
+            w + h + self.max.col - cell.col
+        } else {
+            2 * w + h + self.max.row - cell.row
+        }
+    }
+
+    /// Cell `i` steps from the top-left corner clockwise
+    fn cell_at(&self, i: i32) -> CellPos {
+        let (w, h) = (self.max.col - self.min.col, self.max.row - self.min.row);
+        let i = i.rem_euclid(self.perimeter());
+        if i < w {
+            cell!(self.min.col + i, self.min.row)
+        } else if i < w + h {
+            cell!(self.max.col, self.min.row + i - w)
+        } else if i < 2 * w + h {
+            cell!(self.max.col - (i - w - h), self.max.row)
+        } else {
+            cell!(self.min.col, self.max.row - (i - 2 * w - h))
+        }
+    }
+
+    /// Part of the move `v` from `pnt` along the ring
+    ///
+    /// It goes along an edge, and turns only at the corners.  Moving
+    /// along the row comes first unless it is blocked at a corner.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate bomberhuman; fn main() {
+    /// use bomberhuman::stage::BORDER;
+    ///
+    /// // No way inside from the top edge
+    /// assert_eq!(BORDER.ride(pnt!(120, 0), off!(0, 5)), off!(0, 0));
+    /// // Stopping at the top-right corner to turn
+    /// assert_eq!(BORDER.ride(pnt!(835, 0), off!(10, 10)), off!(5, 0));
+    /// assert_eq!(BORDER.ride(pnt!(840, 0), off!(10, 10)), off!(0, 10));
+    /// # }
+    /// ```
+    pub fn ride(&self, pnt: PixelPos, v: Offset) -> Offset {
+        let (lo, hi) = (self.min.to_pixel(), self.max.to_pixel());
+        let on_row = pnt.y == lo.y || pnt.y == hi.y;
+        let on_col = pnt.x == lo.x || pnt.x == hi.x;
+        let dx = (pnt.x + v.x).clamp(lo.x, hi.x) - pnt.x;
+        let dy = (pnt.y + v.y).clamp(lo.y, hi.y) - pnt.y;
+        if on_row && dx != 0 {
+            off!(dx, 0)
+        } else if on_col {
+            off!(0, dy)
+        } else {
+            off!(0, 0)
+        }
+    }
+}
+
 impl Default for Stage {
     fn default() -> Self {
-        Stage::new()
+        Stage::new(&mut rand::thread_rng(), &[true; MAX_PLAYERS as usize])
     }
 }
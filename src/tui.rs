@@ -0,0 +1,186 @@
+//! Terminal front end
+//!
+//! Renders `GameState` as colored Unicode cells, one cell per grid.
+//! Like the map in `stage.rs`, ■ is a hard block and □ is a soft
+//! block.  Available only on native targets (see `src/bin/tui.rs`).
+
+use std::io::{self, Write};
+
+use crossterm::cursor::MoveTo;
+use crossterm::event::KeyCode;
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::QueueableCommand;
+
+use crate::actors::fire::FireKind;
+use crate::actors::ActorId;
+use crate::game_state::GameState;
+use crate::geometry::*;
+use crate::input_map::InputMap;
+use crate::keyboard::Key;
+
+/// A character on the terminal with its color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub color: Color,
+}
+
+impl Cell {
+    pub fn new(glyph: char, color: Color) -> Self {
+        Cell { glyph, color }
+    }
+
+    /// Empty floor
+    pub fn floor() -> Self {
+        Cell::new('·', Color::DarkGreen)
+    }
+}
+
+/// Screen buffer for the terminal
+///
+/// Each grid of the stage is mapped to one `Cell`, and each `Cell`
+/// takes two columns of the terminal.
+pub struct TerminalScreen {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+}
+
+impl TerminalScreen {
+    /// Create screen for the stage of `width` x `height` grids
+    pub fn new(width: usize, height: usize) -> Self {
+        TerminalScreen {
+            width,
+            height,
+            cells: vec![Cell::floor(); width * height],
+        }
+    }
+
+    /// Create screen fitting to the size of `gs` in pixels
+    pub fn for_game(gs: &GameState) -> Self {
+        TerminalScreen::new(
+            gs.width as usize / GS as usize,
+            gs.height as usize / GS as usize,
+        )
+    }
+
+    /// Cell at (col, row)
+    pub fn cell(&self, col: usize, row: usize) -> Cell {
+        self.cells[row * self.width + col]
+    }
+
+    /// Render all actors in the game into the buffer.
+    ///
+    /// Actors are painted from bottom to top; fire is the topmost
+    /// so that the danger is always visible.
+    pub fn render(&mut self, gs: &GameState) {
+        for c in self.cells.iter_mut() {
+            *c = Cell::floor();
+        }
+        for p in &*gs.powers() {
+            let glyph = match p.actor_id() {
+                ActorId::BombUp => 'b',
+                ActorId::BombPowerUp => 'f',
+                ActorId::SpeedUp => 's',
+                _ => '?',
+            };
+            self.put(p.pnt, Cell::new(glyph, Color::Cyan));
+        }
+        for b in &*gs.blocks() {
+            let cell = if !b.is_soft() {
+                Cell::new('■', Color::Grey)
+            } else if b.is_burning() {
+                Cell::new('▒', Color::Red)
+            } else {
+                Cell::new('□', Color::DarkYellow)
+            };
+            self.put(b.pnt, cell);
+        }
+        for b in &*gs.bombs() {
+            self.put(b.pnt, Cell::new('●', Color::White));
+        }
+        for p in &*gs.players() {
+            if (p.alive() || p.ghost()) && p.visible() {
+                let mut cell = player_cell(p.actor_id());
+                if let Some(team) = gs.rules().team(p.id) {
+                    cell.color = TEAM_COLORS[team as usize % TEAM_COLORS.len()];
+                }
+                self.put(p.pnt, cell);
+            }
+        }
+        for f in &*gs.fires() {
+            let ch = match f.kind {
+                FireKind::Center => '✹',
+                FireKind::Horizontal
+                | FireKind::Tip(Direction::W)
+                | FireKind::Tip(Direction::E) => '━',
+                _ => '┃',
+            };
+            self.put(f.pnt, Cell::new(ch, Color::Yellow));
+        }
+    }
+
+    /// Write the buffer into the terminal `out` at (`left`, `top`).
+    pub fn draw<W: Write>(&self, out: &mut W, left: u16, top: u16) -> io::Result<()> {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let cell = self.cell(col, row);
+                // Move to each cell explicitly, so that the glyphs of
+                // ambiguous width never break the alignment.
+                out.queue(MoveTo(left + col as u16 * 2, top + row as u16))?
+                    .queue(SetForegroundColor(cell.color))?
+                    .queue(Print(cell.glyph))?;
+            }
+        }
+        out.queue(ResetColor)?;
+        out.flush()
+    }
+
+    fn put(&mut self, pnt: PixelPos, cell: Cell) {
+        let CellPos { col, row } = pnt.to_cell();
+        if col < 0 || row < 0 || col as usize >= self.width || row as usize >= self.height {
+            return;
+        }
+        self.cells[row as usize * self.width + col as usize] = cell;
+    }
+}
+
+/// Colors of the players of each team in team battles
+const TEAM_COLORS: [Color; 4] = [Color::Red, Color::Blue, Color::Green, Color::Magenta];
+
+fn player_cell(actor_id: ActorId) -> Cell {
+    match actor_id {
+        ActorId::Player1 => Cell::new('1', Color::Green),
+        ActorId::Player2 => Cell::new('2', Color::Blue),
+        ActorId::Player3 => Cell::new('3', Color::Red),
+        ActorId::Player4 => Cell::new('4', Color::Magenta),
+        ActorId::Player5 => Cell::new('5', Color::DarkGreen),
+        ActorId::Player6 => Cell::new('6', Color::DarkBlue),
+        ActorId::Player7 => Cell::new('7', Color::DarkRed),
+        ActorId::Player8 => Cell::new('8', Color::DarkMagenta),
+        _ => Cell::new('?', Color::White),
+    }
+}
+
+/// Name of a key on the terminal as `KeyboardEvent.key` of the browser
+pub fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Left => "ArrowLeft".to_string(),
+        KeyCode::Right => "ArrowRight".to_string(),
+        KeyCode::Up => "ArrowUp".to_string(),
+        KeyCode::Down => "ArrowDown".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Escape".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Map a key on the terminal into (player, Key) by `map`
+///
+/// The same `InputMap` as the browser version works.
+pub fn key_bind(map: &InputMap, code: KeyCode) -> Option<(u32, Key)> {
+    let bind = map.lookup(&key_name(code)?)?;
+    Some((bind.player, bind.key))
+}
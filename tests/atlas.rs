@@ -0,0 +1,58 @@
+//! Sprite atlas descriptor and animations
+
+use bomberhuman::actors::ActorId;
+use bomberhuman::atlas::{AnimState, Animator, Atlas};
+use bomberhuman::error::Error;
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::KeyState;
+
+#[test]
+fn atlas_can_be_swapped() {
+    let gs = GameState::with_seed(900, 780, 5);
+    let json = r#"{ "Block": { "row": 12, "animations": {
+        "hard": { "frames": [3, 3] },
+        "soft": { "frames": [4, 4] } } } }"#;
+    gs.load_atlas(json).unwrap();
+
+    // Only blocks are in the new atlas.
+    let list = gs.draw_list();
+    assert_eq!(list.len(), gs.blocks().len());
+    assert!(list
+        .iter()
+        .all(|c| c.row == 12 && (c.col == 3 || c.col == 4)));
+
+    let broken = r#"{ "Block": { "row": 1, "animations": {
+        "hard": { "frames": [3, 2] } } } }"#;
+    assert!(matches!(gs.load_atlas(broken), Err(Error::InvalidAtlas(_))));
+    assert!(gs.load_atlas("{").is_err());
+}
+
+#[test]
+fn walking_player_is_animated() {
+    let atlas = Atlas::default();
+    let mut walk = Animator::new(ActorId::Player2, AnimState::WalkE);
+    let frames: Vec<_> = (0..4)
+        .map(|_| {
+            let frame = atlas.frame(&walk);
+            walk.advance(160);
+            frame.unwrap()
+        })
+        .collect();
+    assert_eq!(frames, vec![(1, 6), (1, 7), (1, 8), (1, 6)]);
+
+    let mut gs = GameState::with_seed(900, 780, 5);
+    let mut keys = vec![KeyState::new(); 4];
+    // Walk out of the corner toward the middle.
+    let top = gs.players().iter().find(|p| p.id == 0).unwrap().pnt.y < 390;
+    keys[0].down = top;
+    keys[0].up = !top;
+    let walk = if top { 0..=2 } else { 9..=11 };
+    let mut cols = vec![];
+    for _ in 0..30 {
+        gs.step_with(&keys);
+        let first = gs.draw_list().iter().find(|c| c.row == 0).unwrap();
+        cols.push(first.col);
+    }
+    assert!(cols.iter().all(|c| walk.contains(c)), "{:?}", cols);
+    assert!(walk.clone().all(|c| cols.contains(&c)), "{:?}", cols);
+}
@@ -0,0 +1,36 @@
+//! Procedural sound synthesis
+
+use bomberhuman::audio::synth::{self, Music, Sound};
+
+#[test]
+fn sounds_are_audible_and_bounded() {
+    for sound in &[
+        Sound::Explosion(2),
+        Sound::FuseTick,
+        Sound::Pickup,
+        Sound::Death,
+    ] {
+        let samples = sound.render(44100);
+        assert_eq!(samples.len(), (sound.duration() * 44100.0) as usize);
+        assert!(samples.iter().all(|s| s.abs() <= 1.0), "{:?}", sound);
+        let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak > 0.1, "{:?} is too quiet: {}", sound, peak);
+    }
+}
+
+#[test]
+fn music_speeds_up_at_the_end_of_round() {
+    assert_eq!(synth::tempo(60_000), synth::BASE_BPM);
+    assert!(synth::tempo(10_000) > synth::tempo(20_000));
+    assert_eq!(synth::tempo(0), synth::MAX_BPM);
+
+    let mut buf = vec![0.0; 22050];
+    let mut normal = Music::new();
+    let mut hurry = Music::new();
+    for _ in 0..4 {
+        normal.render(&mut buf, 22050, synth::tempo(60_000));
+        hurry.render(&mut buf, 22050, synth::tempo(0));
+    }
+    assert!(hurry.steps() > normal.steps());
+    assert!(buf.iter().any(|&s| s != 0.0));
+}
@@ -0,0 +1,12 @@
+//! Setup shared by the tests
+
+use bomberhuman::game_state::GameState;
+use bomberhuman::rules::Rules;
+
+/// Game of `rules` with only hard blocks and no items
+pub fn open_game(rules: Rules) -> GameState {
+    let gs = GameState::with_rules(900, 780, 1, rules);
+    gs.blocks_mut().retain(|b| !b.is_soft());
+    gs.powers_mut().clear();
+    gs
+}
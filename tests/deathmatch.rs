@@ -0,0 +1,90 @@
+//! Timed deathmatch and kill scoring
+
+use bomberhuman::actors::bomb::Bomb;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::{GameState, Score};
+use bomberhuman::rules::{Mode, Rules};
+
+mod common;
+
+fn deathmatch(players: u32, round_secs: u32) -> GameState {
+    let rules = Rules {
+        players,
+        round_secs,
+        mode: Mode::Deathmatch,
+        ..Rules::default()
+    };
+    common::open_game(rules)
+}
+
+/// Wait for `victim` to come back, and blow it up by a bomb of `owner`.
+fn kill(gs: &mut GameState, victim: u32, owner: u32) -> Vec<GameEvent> {
+    let alive = |gs: &GameState| gs.players().iter().any(|p| p.id == victim && p.alive());
+    while !alive(gs) {
+        gs.step_with(&[]);
+    }
+    let pnt = gs.players().iter().find(|p| p.id == victim).unwrap().pnt;
+    gs.bombs_mut().push(Bomb::new(owner, pnt.x, pnt.y, 1));
+    let mut events = vec![];
+    while alive(gs) {
+        gs.step_with(&[]);
+        events.extend(gs.take_events());
+    }
+    events
+}
+
+/// Events until the end of the round
+fn play_round(gs: &mut GameState) -> Vec<GameEvent> {
+    let mut events = vec![];
+    while !events
+        .iter()
+        .any(|e| matches!(e, GameEvent::RoundOver { .. }))
+    {
+        gs.step_with(&[]);
+        events.extend(gs.take_events());
+    }
+    events
+}
+
+#[test]
+fn kills_go_to_the_owner_of_the_bomb() {
+    let mut gs = deathmatch(3, 60);
+    let events = kill(&mut gs, 0, 1);
+    assert!(events.iter().any(|e| matches!(
+        e,
+        GameEvent::PlayerDied {
+            player: 0,
+            killer: 1,
+            ..
+        }
+    )));
+    kill(&mut gs, 0, 1);
+    kill(&mut gs, 2, 2);
+
+    assert_eq!(
+        gs.score(1),
+        Score {
+            kills: 2,
+            suicides: 0
+        }
+    );
+    assert_eq!(gs.points(2), -1);
+    assert_eq!(gs.points(0), 0);
+    assert!(gs.players().iter().all(|p| !p.out()));
+}
+
+#[test]
+fn top_score_wins_when_time_is_up() {
+    let mut gs = deathmatch(2, 20);
+    kill(&mut gs, 0, 1);
+    let events = play_round(&mut gs);
+    assert_eq!(gs.time_left(), 0);
+    assert!(events.contains(&GameEvent::RoundOver { winner: Some(1) }));
+    assert_eq!((gs.wins(0), gs.wins(1)), (0, 1));
+
+    // Scores start over, and a tie is a draw.
+    gs.next_round();
+    assert_eq!(gs.points(1), 0);
+    let events = play_round(&mut gs);
+    assert!(events.contains(&GameEvent::RoundOver { winner: None }));
+}
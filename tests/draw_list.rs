@@ -0,0 +1,86 @@
+//! Draw commands made without the browser
+
+use bomberhuman::actors::ActorId;
+use bomberhuman::game_state::GameState;
+use bomberhuman::hud::HUD_HEIGHT;
+use bomberhuman::keyboard::KeyState;
+use bomberhuman::screen::DrawCommand;
+
+/// Rows in `sprites.json`
+const FLOOR_ROW: i32 = 17;
+const FONT_ROW: i32 = 18;
+
+fn arena(gs: &GameState) -> Vec<DrawCommand> {
+    gs.draw_list()
+        .iter()
+        .filter(|c| c.y >= HUD_HEIGHT)
+        .collect()
+}
+
+#[test]
+fn draw_list_has_every_actor() {
+    let mut gs = GameState::with_seed(900, 780, 3);
+    // Items hidden under blocks are drawn too.
+    let actors = gs.blocks().len() + gs.players().len() + gs.powers().len();
+    let cells = 15 * 13;
+    assert_eq!(arena(&gs).len(), actors + cells);
+
+    let mut keys = vec![KeyState::new(); 4];
+    keys[0].button1 = true;
+    gs.step_with(&keys);
+    let list = arena(&gs);
+    let bomb = list
+        .iter()
+        .find(|c| c.row == ActorId::Bomb as i32)
+        .expect("bomb should be drawn");
+    let b = &gs.bombs()[0];
+    assert_eq!((bomb.x, bomb.y - HUD_HEIGHT), (b.pnt.x, b.pnt.y));
+}
+
+#[test]
+fn draw_list_is_layered() {
+    let mut gs = GameState::with_seed(900, 780, 3);
+    let mut keys = vec![KeyState::new(); 4];
+    keys[0].button1 = true;
+    gs.step_with(&keys);
+
+    let list = gs.draw_list();
+    let rows: Vec<i32> = list.iter().map(|c| c.row).collect();
+    let first = |row: i32| rows.iter().position(|&r| r == row).unwrap();
+    let last = |row: i32| rows.iter().rposition(|&r| r == row).unwrap();
+
+    // The floor first, the player over the bomb under the feet, and
+    // the HUD at last
+    assert!(rows[..=last(FLOOR_ROW)].iter().all(|&r| r == FLOOR_ROW));
+    assert!(last(ActorId::Bomb as i32) < first(ActorId::Player1 as i32));
+    assert_eq!(last(FONT_ROW), rows.len() - 1);
+
+    // Players are sorted by y.
+    let ys: Vec<i32> = arena(&gs)
+        .iter()
+        .filter(|c| (0..4).contains(&c.row))
+        .map(|c| c.y)
+        .collect();
+    assert!(ys.windows(2).all(|w| w[0] <= w[1]), "{:?}", ys);
+}
+
+#[test]
+fn hud_shows_stats_and_timer() {
+    let gs = GameState::with_seed(900, 780, 3);
+    let stats = gs.player_stats();
+    assert_eq!(stats.len(), 4);
+    assert!(stats
+        .iter()
+        .all(|s| s.bombs == 1 && s.power == 2 && s.wins == 0));
+
+    let hud: Vec<DrawCommand> = gs
+        .draw_list()
+        .iter()
+        .filter(|c| c.y < HUD_HEIGHT && c.row != FLOOR_ROW)
+        .collect();
+    // A portrait and "b1 f2" "s1 w0" for each, and "3:00"
+    let glyphs = hud.iter().filter(|c| c.row == FONT_ROW).count();
+    assert_eq!(glyphs, 4 * 8 + 4);
+    let portraits = hud.iter().filter(|c| c.row != FONT_ROW).count();
+    assert_eq!(portraits, 4);
+}
@@ -0,0 +1,33 @@
+//! Game events drained by front ends
+
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::KeyState;
+
+#[test]
+fn bomber_standing_on_own_bomb_dies() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    let mut keys = vec![KeyState::new(); 4];
+    keys[0].button1 = true;
+    gs.step_with(&keys);
+    keys[0].button1 = false;
+
+    let mut events = gs.take_events();
+    for _ in 0..300 {
+        gs.step_with(&keys);
+        events.append(&mut gs.take_events());
+    }
+    assert!(gs.take_events().is_empty());
+
+    let place = events
+        .iter()
+        .position(|e| matches!(e, GameEvent::BombPlaced { player: 0, .. }));
+    let explode = events
+        .iter()
+        .position(|e| matches!(e, GameEvent::Exploded { owner: 0, .. }));
+    let die = events
+        .iter()
+        .position(|e| matches!(e, GameEvent::PlayerDied { player: 0, .. }));
+    assert!(place < explode && explode < die, "{:?}", events);
+    assert!(place.is_some());
+}
@@ -0,0 +1,35 @@
+//! Shapes of explosions
+
+use bomberhuman::actors::fire::FireKind;
+use bomberhuman::game_state::GameState;
+use bomberhuman::geometry::Direction;
+use bomberhuman::keyboard::KeyState;
+
+#[test]
+fn explosion_has_center_and_tips() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    let mut keys = vec![KeyState::new(); 4];
+    keys[0].button1 = true;
+    gs.step_with(&keys);
+    keys[0].button1 = false;
+    let bomb = gs.bombs()[0].pnt;
+
+    while gs.fires().is_empty() {
+        gs.step_with(&keys);
+    }
+    let fires = gs.fires();
+    // Power 2 reaches the next cells except walls.
+    assert!(fires.len() >= 2 && fires.len() <= 5, "{:?}", fires);
+    for f in fires.iter() {
+        let d = f.pnt - bomb;
+        let expected = match (d.x.signum(), d.y.signum()) {
+            (0, 0) => FireKind::Center,
+            (0, -1) => FireKind::Tip(Direction::N),
+            (0, 1) => FireKind::Tip(Direction::S),
+            (-1, 0) => FireKind::Tip(Direction::W),
+            (1, 0) => FireKind::Tip(Direction::E),
+            _ => panic!("fire off the arms: {:?}", f),
+        };
+        assert_eq!(f.kind, expected);
+    }
+}
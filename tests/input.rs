@@ -0,0 +1,96 @@
+//! Key bindings and edges of keys
+
+use bomberhuman::actors::ActorId;
+use bomberhuman::error::Error;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::input_map::{Binding, InputMap};
+use bomberhuman::keyboard::Key;
+
+#[test]
+fn holding_button_puts_one_bomb() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    gs.players_mut()
+        .iter_mut()
+        .for_each(|p| p.push_item(ActorId::BombUp));
+
+    gs.toggle_key(0, Key::Button1, true).unwrap();
+    let mut placed = 0;
+    for _ in 0..20 {
+        gs.update(16);
+        placed += gs
+            .take_events()
+            .iter()
+            .filter(|e| matches!(e, GameEvent::BombPlaced { player: 0, .. }))
+            .count();
+    }
+    assert_eq!(placed, 1);
+}
+
+#[test]
+fn opposite_directions_cancel_out() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    let start = gs.players()[0].pnt;
+    gs.toggle_key(0, Key::Left, true).unwrap();
+    gs.toggle_key(0, Key::Right, true).unwrap();
+    for _ in 0..20 {
+        gs.update(16);
+    }
+    assert_eq!(gs.players()[0].pnt, start);
+}
+
+#[test]
+fn input_map_survives_settings_blob() {
+    let mut map = InputMap::new();
+    map.bind("x", 1, Key::Button1).unwrap();
+    map.unbind("q");
+    let json = map.to_json();
+    let loaded = InputMap::from_json(&json).unwrap();
+
+    assert_eq!(loaded, map);
+    assert_eq!(
+        loaded.lookup("x"),
+        Some(Binding {
+            player: 1,
+            key: Key::Button1
+        })
+    );
+    assert_eq!(loaded.lookup("q"), None);
+    assert!(matches!(
+        InputMap::from_json("{\"x\": 1}"),
+        Err(Error::InvalidInputMap(_))
+    ));
+
+    // Bindings to no player are rejected as when loaded.
+    assert!(matches!(
+        map.bind("y", 9, Key::Left),
+        Err(Error::NoSuchPlayer(9))
+    ));
+    assert!(matches!(
+        map.rebind("x", 8, Key::Button1),
+        Err(Error::NoSuchPlayer(8))
+    ));
+    assert_eq!(map.lookup("y"), None);
+    assert_eq!(map.lookup("x").unwrap().player, 1);
+}
+
+#[test]
+fn analog_stick_rests_in_dead_zone() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    gs.blocks_mut().clear();
+    let p0 = |gs: &GameState| gs.players().iter().find(|p| p.id == 0).unwrap().pnt;
+    let start = p0(&gs);
+
+    gs.set_axis(0, 0.15, -0.1).unwrap();
+    for _ in 0..20 {
+        gs.update(16);
+    }
+    assert_eq!(p0(&gs), start);
+
+    gs.set_axis(0, -0.8, 0.1).unwrap();
+    for _ in 0..20 {
+        gs.update(16);
+    }
+    let pnt = p0(&gs);
+    assert!(pnt.x < start.x && pnt.y == start.y, "{:?}", pnt);
+}
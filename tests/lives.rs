@@ -0,0 +1,165 @@
+//! Lives, respawn and spawn invulnerability
+
+use bomberhuman::actors::bomb::Bomb;
+use bomberhuman::actors::power::Power;
+use bomberhuman::actors::ActorId;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::geometry::PixelPos;
+use bomberhuman::rules::{Mode, Rules};
+
+mod common;
+
+/// Game of players 0 and 1 in `mode` with player 0 away from the spawn
+fn game(mode: Mode) -> GameState {
+    let rules = Rules {
+        players: 2,
+        mode,
+        ..Rules::default()
+    };
+    common::open_game(rules)
+}
+
+fn player0(gs: &GameState) -> (PixelPos, bool, u32) {
+    let players = gs.players();
+    let p = players.iter().find(|p| p.id == 0).unwrap();
+    (p.pnt, p.alive(), p.lives())
+}
+
+/// Blow up player 0 by a bomb of player 1, and return the events
+/// until the fire goes out
+fn blow_up(gs: &mut GameState) -> Vec<GameEvent> {
+    let (pnt, _, _) = player0(gs);
+    gs.bombs_mut().push(Bomb::new(1, pnt.x, pnt.y, 1));
+    let mut events = vec![];
+    for _ in 0..330 {
+        gs.step_with(&[]);
+        events.extend(gs.take_events());
+        if !player0(gs).1 && gs.fires().is_empty() {
+            break;
+        }
+    }
+    events
+}
+
+#[test]
+fn player_comes_back_at_spawn_until_lives_run_out() {
+    let mut gs = game(Mode::Lives(2));
+    let spawn = gs.players().iter().find(|p| p.id == 0).unwrap().spawn();
+    gs.players_mut().iter_mut().find(|p| p.id == 0).unwrap().pnt = PixelPos::new(420, 420);
+
+    blow_up(&mut gs);
+    assert_eq!(player0(&gs).2, 1);
+    let mut events = vec![];
+    for _ in 0..200 {
+        gs.step_with(&[]);
+        events.extend(gs.take_events());
+    }
+    let (pnt, alive, _) = player0(&gs);
+    assert!(alive);
+    assert_eq!(pnt, spawn);
+    assert!(events.contains(&GameEvent::PlayerRespawned {
+        player: 0,
+        x: spawn.x,
+        y: spawn.y,
+    }));
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, GameEvent::RoundOver { .. })));
+
+    // Blinking and not hurt for a while
+    let p = gs.players().iter().find(|p| p.id == 0).unwrap().clone();
+    assert!(p.invulnerable());
+    let visible: Vec<bool> = (0..20)
+        .map(|_| {
+            gs.step_with(&[]);
+            gs.players().iter().find(|p| p.id == 0).unwrap().visible()
+        })
+        .collect();
+    assert!(visible.contains(&true) && visible.contains(&false));
+    for _ in 0..200 {
+        gs.step_with(&[]);
+    }
+
+    let events = blow_up(&mut gs);
+    assert_eq!(player0(&gs).2, 0);
+    assert!(events.contains(&GameEvent::RoundOver { winner: Some(1) }));
+    for _ in 0..600 {
+        gs.step_with(&[]);
+    }
+    assert!(!player0(&gs).1);
+}
+
+#[test]
+fn player_comes_back_out_of_danger() {
+    let mut gs = game(Mode::Lives(3));
+    let spawn = gs.players().iter().find(|p| p.id == 0).unwrap().spawn();
+    gs.players_mut().iter_mut().find(|p| p.id == 0).unwrap().pnt = PixelPos::new(420, 420);
+    blow_up(&mut gs);
+
+    gs.bombs_mut().push(Bomb::new(1, spawn.x, spawn.y, 3));
+    for _ in 0..200 {
+        gs.step_with(&[]);
+        if player0(&gs).1 {
+            break;
+        }
+    }
+    let (pnt, alive, _) = player0(&gs);
+    assert!(alive);
+    // Out of the blast reaching 2 cells from the spawn
+    let d = pnt - spawn;
+    assert!(
+        (d.x != 0 && d.y != 0) || d.x.abs() + d.y.abs() > 2 * 60,
+        "{:?}",
+        pnt
+    );
+}
+
+#[test]
+fn last_man_standing_has_no_respawn() {
+    let mut gs = game(Mode::LastManStanding);
+    blow_up(&mut gs);
+    for _ in 0..1000 {
+        gs.step_with(&[]);
+    }
+    assert!(!player0(&gs).1);
+    assert_eq!(gs.wins(1), 1);
+}
+
+#[test]
+fn player_comes_back_off_the_others() {
+    let mut gs = game(Mode::Lives(2));
+    let spawn = gs.players().iter().find(|p| p.id == 0).unwrap().spawn();
+    gs.players_mut().iter_mut().find(|p| p.id == 0).unwrap().pnt = PixelPos::new(420, 420);
+    blow_up(&mut gs);
+    gs.players_mut().iter_mut().find(|p| p.id == 1).unwrap().pnt = spawn;
+
+    for _ in 0..200 {
+        gs.step_with(&[]);
+        if player0(&gs).1 {
+            break;
+        }
+    }
+    let (pnt, alive, _) = player0(&gs);
+    assert!(alive);
+    assert!(!pnt.collides_with(spawn), "{:?}", pnt);
+    assert_eq!((pnt - spawn).length(), 60);
+}
+
+#[test]
+fn dead_player_picks_up_no_items() {
+    let mut gs = game(Mode::Lives(2));
+    gs.players_mut().iter_mut().find(|p| p.id == 0).unwrap().pnt = PixelPos::new(420, 420);
+    blow_up(&mut gs);
+    gs.powers_mut().push(Power::new(420, 420, ActorId::BombUp));
+
+    let mut events = vec![];
+    while !player0(&gs).1 {
+        gs.step_with(&[]);
+        events.extend(gs.take_events());
+    }
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, GameEvent::ItemPicked { .. })));
+    assert_eq!(gs.powers().len(), 1);
+}
@@ -0,0 +1,111 @@
+//! Online lockstep mode without the relay
+
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::{Key, KeyState};
+use bomberhuman::netplay::Message;
+
+fn start(player: u32) -> GameState {
+    start_of(player, 2)
+}
+
+fn start_of(player: u32, players: u32) -> GameState {
+    let mut gs = GameState::new(900, 780);
+    gs.net_join();
+    let start = Message::Start {
+        player,
+        players,
+        seed: 3,
+        input_delay: 2,
+    };
+    gs.net_receive(&start.encode()).unwrap();
+    gs
+}
+
+/// Deliver all messages from `from` to `to` as the relay does
+fn relay(from: &mut GameState, to: &mut GameState) {
+    while let Some(data) = from.net_poll() {
+        to.net_receive(&data).unwrap();
+    }
+}
+
+#[test]
+fn peers_advance_together() {
+    let (mut a, mut b) = (start(0), start(1));
+    a.toggle_key(0, Key::Down, true).unwrap();
+    b.toggle_key(0, Key::Left, true).unwrap();
+
+    // `a` can run ahead only by the input delay without `b`.
+    for _ in 0..10 {
+        a.update(16);
+    }
+    assert_eq!(a.tick(), 2);
+
+    for _ in 0..100 {
+        a.update(16);
+        b.update(16);
+        relay(&mut a, &mut b);
+        relay(&mut b, &mut a);
+    }
+    while a.tick() != b.tick() {
+        a.update(16);
+        b.update(16);
+    }
+    assert_eq!(a.state_hash(), b.state_hash());
+}
+
+#[test]
+fn disconnected_player_is_not_waited_for() {
+    let mut a = start(0);
+    for _ in 0..10 {
+        a.update(16);
+    }
+    assert_eq!(a.tick(), 2);
+
+    a.net_receive(&Message::Leave { player: 1 }.encode())
+        .unwrap();
+    for _ in 0..10 {
+        a.update(16);
+    }
+    // Catches up the time it has been waiting, too.
+    assert!(a.tick() >= 12);
+}
+
+#[test]
+fn peers_told_of_leave_at_different_ticks_stay_in_sync() {
+    let (mut a, mut b) = (start_of(0, 3), start_of(1, 3));
+    // Player 2 walks down for a while before leaving.
+    let mut down = KeyState::new();
+    down.down = true;
+    for tick in 2..40 {
+        let input = Message::Input {
+            tick,
+            player: 2,
+            keys: down.to_bits(),
+        };
+        a.net_receive(&input.encode()).unwrap();
+        b.net_receive(&input.encode()).unwrap();
+    }
+    let leave = Message::Leave { player: 2 }.encode();
+
+    // `a` is told before simulating those ticks, and `b` after.
+    a.net_receive(&leave).unwrap();
+    for _ in 0..100 {
+        a.update(16);
+        b.update(16);
+        relay(&mut a, &mut b);
+        relay(&mut b, &mut a);
+    }
+    assert!(b.tick() >= 40);
+    b.net_receive(&leave).unwrap();
+    for _ in 0..100 {
+        a.update(16);
+        b.update(16);
+        relay(&mut a, &mut b);
+        relay(&mut b, &mut a);
+    }
+    while a.tick() != b.tick() {
+        a.update(16);
+        b.update(16);
+    }
+    assert_eq!(a.state_hash(), b.state_hash());
+}
@@ -0,0 +1,103 @@
+//! Collision response of player movement
+
+#[macro_use]
+extern crate bomberhuman;
+
+use bomberhuman::actors::block::Block;
+use bomberhuman::actors::ActorId;
+use bomberhuman::game_state::GameState;
+use bomberhuman::geometry::*;
+use bomberhuman::keyboard::KeyState;
+use bomberhuman::rules::Rules;
+
+/// Game with player 0 at `start` and hard blocks at `walls` only
+fn arena(start: PixelPos, walls: &[PixelPos]) -> GameState {
+    arena_with_rules(start, walls, Rules::default())
+}
+
+fn arena_with_rules(start: PixelPos, walls: &[PixelPos], rules: Rules) -> GameState {
+    let gs = GameState::with_rules(900, 780, 1, rules);
+    gs.players_mut().retain(|p| p.id == 0);
+    gs.players_mut()[0].pnt = start;
+    gs.powers_mut().clear();
+    *gs.blocks_mut() = walls.iter().map(|w| Block::hard(w.x, w.y)).collect();
+    gs
+}
+
+fn walk(gs: &mut GameState, keys: &KeyState, ticks: usize) -> PixelPos {
+    for _ in 0..ticks {
+        gs.step_with(std::slice::from_ref(keys));
+    }
+    gs.players()[0].pnt
+}
+
+#[test]
+fn player_stops_at_contact_with_wall() {
+    let mut gs = arena(pnt!(60, 60), &[pnt!(180, 60)]);
+    // 3.44 pixels per tick does not divide the distance of 60.
+    for _ in 0..3 {
+        gs.players_mut()[0].push_item(ActorId::SpeedUp);
+    }
+    let mut right = KeyState::new();
+    right.right = true;
+    assert_eq!(walk(&mut gs, &right, 30), pnt!(120, 60));
+}
+
+#[test]
+fn player_slips_around_corner_into_open_lane() {
+    let mut gs = arena(pnt!(60, 85), &[pnt!(120, 60)]);
+    let mut right = KeyState::new();
+    right.right = true;
+    let pnt = walk(&mut gs, &right, 40);
+    assert_eq!(pnt.y, 120);
+    assert!(pnt.x > 60, "{:?}", pnt);
+}
+
+#[test]
+fn sub_pixel_moves_add_up() {
+    // 1.6 pixels per tick of 16ms
+    let rules = Rules {
+        speed_table: vec![100],
+        ..Rules::default()
+    };
+    let mut gs = arena_with_rules(pnt!(60, 60), &[], rules);
+    let mut right = KeyState::new();
+    right.right = true;
+    assert_eq!(walk(&mut gs, &right, 10), pnt!(76, 60));
+}
+
+#[test]
+fn player_never_tunnels_through_wall() {
+    let rules = Rules {
+        speed_table: vec![125, 1000, 4000, 10000],
+        ..Rules::default()
+    };
+    for level in 1..=4 {
+        let mut gs = arena_with_rules(pnt!(60, 60), &[pnt!(240, 60)], rules.clone());
+        for _ in 1..level {
+            gs.players_mut()[0].push_item(ActorId::SpeedUp);
+        }
+        let mut right = KeyState::new();
+        right.right = true;
+        assert_eq!(walk(&mut gs, &right, 200), pnt!(180, 60), "level {}", level);
+    }
+}
+
+#[test]
+fn diagonal_goes_along_closer_lane() {
+    let mut down_right = KeyState::new();
+    down_right.set_axis(0.7, 0.7);
+
+    let mut gs = arena(pnt!(60, 70), &[]);
+    let pnt = walk(&mut gs, &down_right, 5);
+    assert!(pnt.x == 60 && pnt.y > 70, "{:?}", pnt);
+
+    let mut gs = arena(pnt!(70, 60), &[]);
+    let pnt = walk(&mut gs, &down_right, 5);
+    assert!(pnt.x > 70 && pnt.y == 60, "{:?}", pnt);
+
+    // Blocked ahead on the right, and turns down.
+    let mut gs = arena(pnt!(60, 60), &[pnt!(120, 60)]);
+    let pnt = walk(&mut gs, &down_right, 5);
+    assert!(pnt.x == 60 && pnt.y > 60, "{:?}", pnt);
+}
@@ -0,0 +1,109 @@
+//! Number of players, joining and leaving
+
+use bomberhuman::atlas::Atlas;
+use bomberhuman::error::Error;
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::Key;
+use bomberhuman::rules::{Rules, MAX_PLAYERS};
+
+fn ids(gs: &GameState) -> Vec<u32> {
+    let mut ids: Vec<u32> = gs.players().iter().map(|p| p.id).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn eight_players_start_apart_in_their_colors() {
+    let rules = Rules {
+        players: MAX_PLAYERS,
+        ..Rules::default()
+    };
+    let gs = GameState::with_rules(900, 780, 1, rules);
+    assert_eq!(ids(&gs), (0..MAX_PLAYERS).collect::<Vec<_>>());
+
+    let players = gs.players();
+    let mut cells: Vec<_> = players.iter().map(|p| p.pnt.to_cell()).collect();
+    cells.sort_by_key(|c| (c.col, c.row));
+    cells.dedup();
+    assert_eq!(cells.len(), MAX_PLAYERS as usize);
+
+    let atlas = Atlas::default();
+    let palettes: Vec<u32> = (0..MAX_PLAYERS)
+        .map(|id| {
+            let p = players.iter().find(|p| p.id == id).unwrap();
+            atlas.palette(p.actor_id())
+        })
+        .collect();
+    assert_eq!(palettes, vec![0, 0, 0, 0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn players_join_and_leave_between_rounds() {
+    let rules = Rules {
+        players: 2,
+        ..Rules::default()
+    };
+    let mut gs = GameState::with_rules(900, 780, 1, rules);
+    gs.join(5).unwrap();
+    gs.leave(1).unwrap();
+    assert_eq!(ids(&gs), vec![0, 1]);
+    assert!(gs.seated(5) && !gs.seated(1));
+
+    gs.next_round();
+    assert_eq!(ids(&gs), vec![0, 5]);
+}
+
+#[test]
+fn out_of_range_player_is_an_error() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    assert_eq!(
+        gs.toggle_key(MAX_PLAYERS, Key::Left, true),
+        Err(Error::NoSuchPlayer(MAX_PLAYERS))
+    );
+    assert_eq!(gs.set_axis(99, 1.0, 0.0), Err(Error::NoSuchPlayer(99)));
+    assert_eq!(gs.join(MAX_PLAYERS), Err(Error::NoSuchPlayer(MAX_PLAYERS)));
+    assert!(gs.toggle_key(MAX_PLAYERS - 1, Key::Left, true).is_ok());
+}
+
+#[test]
+fn cells_around_extra_spawns_are_open_only_for_their_players() {
+    // Spawns of players 4 to 7 and the cells next to them
+    let extra = [
+        (6, 1),
+        (7, 1),
+        (8, 1),
+        (7, 2),
+        (6, 11),
+        (7, 11),
+        (8, 11),
+        (7, 10),
+        (1, 5),
+        (1, 6),
+        (1, 7),
+        (13, 5),
+        (13, 6),
+        (13, 7),
+    ];
+    let blocked = |players: u32| {
+        (1..20)
+            .map(|seed| {
+                let rules = Rules {
+                    players,
+                    ..Rules::default()
+                };
+                let gs = GameState::with_rules(900, 780, seed, rules);
+                let blocks = gs.blocks();
+                blocks
+                    .iter()
+                    .filter(|b| {
+                        let c = b.pnt.to_cell();
+                        extra.contains(&(c.col, c.row))
+                    })
+                    .count()
+            })
+            .sum::<usize>()
+    };
+    assert_eq!(blocked(MAX_PLAYERS), 0);
+    // Soft blocks may be there as in the classic stage.
+    assert!(blocked(4) > 0);
+}
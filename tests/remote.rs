@@ -0,0 +1,102 @@
+//! Snapshot deltas and the remote mode without the server
+
+use bomberhuman::delta;
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::KeyState;
+use bomberhuman::netplay::Message;
+
+/// Keys of players putting bombs every 50 ticks and walking around
+fn keys(tick: u32) -> Vec<KeyState> {
+    (0..4)
+        .map(|p| {
+            let mut ks = KeyState::new();
+            ks.button1 = tick % 50 == p * 5;
+            ks.down = (tick / 40 + p) % 2 == 0;
+            ks.right = !ks.down;
+            ks
+        })
+        .collect()
+}
+
+#[test]
+fn deltas_turn_snapshots_into_the_next_ones() {
+    let mut gs = GameState::with_seed(900, 780, 5);
+    let mut old = gs.snapshot();
+    let mut resized = 0;
+    for tick in 0..600 {
+        if tick == 400 {
+            // Every actor changes at once.
+            gs.next_round();
+        }
+        gs.step_with(&keys(tick));
+        let new = gs.snapshot();
+        let d = delta::encode(&old, &new);
+        assert_eq!(delta::apply(&old, &d).unwrap(), new, "tick {}", tick);
+        if new.len() != old.len() {
+            resized += 1;
+        }
+        old = new;
+    }
+    // Bombs and fire came and went, and blocks burned.
+    assert!(resized > 10, "{}", resized);
+}
+
+#[test]
+fn broken_deltas_are_errors() {
+    let old = vec![1, 2, 3, 4];
+    let cases: Vec<Vec<u8>> = vec![
+        vec![],
+        // Cut in the middle of a run
+        vec![4, 0, 2, 9],
+        // Run beyond the length
+        vec![4, 3, 2, 9, 9],
+        // Length far beyond the data
+        vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+        // Skip overflowing the position
+        vec![
+            4, 0, 1, 9, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0, 0,
+        ],
+        // Varint longer than usize
+        vec![0xff; 12],
+    ];
+    for d in cases {
+        assert!(delta::apply(&old, &d).is_err(), "{:?}", d);
+    }
+}
+
+#[test]
+fn remote_client_follows_the_server() {
+    let mut server = GameState::with_seed(900, 780, 7);
+    let mut client = GameState::with_seed(900, 780, 1);
+    client.net_watch();
+
+    let mut last: Option<Vec<u8>> = None;
+    for tick in 0..300 {
+        server.step_with(&keys(tick));
+        let state = server.snapshot();
+        let msg = match &last {
+            None => Message::State {
+                tick,
+                data: state.clone(),
+            },
+            Some(old) => Message::Delta {
+                tick,
+                data: delta::encode(old, &state),
+            },
+        };
+        client.net_receive(&msg.encode()).unwrap();
+        // The client does not simulate by itself.
+        client.update(16);
+        assert_eq!(client.state_hash(), server.state_hash(), "tick {}", tick);
+        last = Some(state);
+    }
+
+    // A delta without the whole state first is rejected.
+    let mut late = GameState::with_seed(900, 780, 1);
+    late.net_watch();
+    let msg = Message::Delta {
+        tick: 0,
+        data: delta::encode(&[], &server.snapshot()),
+    };
+    assert!(late.net_receive(&msg.encode()).is_err());
+}
@@ -0,0 +1,147 @@
+//! Revenge ghosts riding the outer wall
+
+#[macro_use]
+extern crate bomberhuman;
+
+use bomberhuman::actors::bomb::Bomb;
+use bomberhuman::actors::player::Player;
+use bomberhuman::bot;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::geometry::*;
+use bomberhuman::keyboard::KeyState;
+use bomberhuman::rules::Rules;
+
+mod common;
+
+fn revenge() -> GameState {
+    let rules = Rules {
+        players: 3,
+        revenge: true,
+        ..Rules::default()
+    };
+    common::open_game(rules)
+}
+
+fn player(gs: &GameState, id: u32) -> Player {
+    gs.players().iter().find(|p| p.id == id).unwrap().clone()
+}
+
+/// Step with `keys` of player 0 until `done`, and return the events
+fn step_until<F: Fn(&GameState) -> bool>(
+    gs: &mut GameState,
+    keys: &KeyState,
+    done: F,
+) -> Vec<GameEvent> {
+    let mut events = vec![];
+    for _ in 0..600 {
+        if done(gs) {
+            return events;
+        }
+        gs.step_with(std::slice::from_ref(keys));
+        events.extend(gs.take_events());
+    }
+    panic!("timed out: {:?}", events);
+}
+
+fn on_border(pnt: PixelPos) -> bool {
+    pnt.x == 0 || pnt.x == 840 || pnt.y == 0 || pnt.y == 720
+}
+
+#[test]
+fn player_out_rides_the_outer_wall() {
+    let mut gs = revenge();
+    // Player 0 starts at the bottom-right (13, 11).
+    gs.bombs_mut().push(Bomb::new(0, 780, 660, 1));
+    let idle = KeyState::new();
+    let events = step_until(&mut gs, &idle, |gs| player(gs, 0).ghost());
+
+    let p = player(&gs, 0);
+    assert!(p.out() && !p.alive());
+    assert_eq!(p.pnt, pnt!(840, 660));
+    assert!(events.contains(&GameEvent::BecameGhost {
+        player: 0,
+        x: 840,
+        y: 660
+    }));
+
+    // Down to the corner, and left along the bottom edge
+    let mut keys = KeyState::new();
+    keys.down = true;
+    keys.left = true;
+    for _ in 0..120 {
+        gs.step_with(&[keys.clone()]);
+        assert!(on_border(player(&gs, 0).pnt), "{:?}", player(&gs, 0).pnt);
+    }
+    let pnt = player(&gs, 0).pnt;
+    assert!(pnt.y == 720 && pnt.x < 840, "{:?}", pnt);
+}
+
+#[test]
+fn ghost_killing_a_rival_swaps_in() {
+    let mut gs = revenge();
+    gs.bombs_mut().push(Bomb::new(0, 780, 660, 1));
+    let idle = KeyState::new();
+    step_until(&mut gs, &idle, |gs| player(gs, 0).ghost());
+
+    // Player 0 on the top wall over player 1 three cells below
+    for p in gs.players_mut().iter_mut() {
+        match p.id {
+            0 => p.pnt = pnt!(60, 0),
+            1 => p.pnt = pnt!(60, 180),
+            _ => (),
+        }
+    }
+    let mut lob = KeyState::new();
+    lob.button1 = true;
+    let events = step_until(&mut gs, &lob, |gs| !gs.bombs().is_empty());
+    assert!(events.contains(&GameEvent::BombPlaced {
+        player: 0,
+        x: 60,
+        y: 180
+    }));
+
+    let events = step_until(&mut gs, &idle, |gs| !player(gs, 0).ghost());
+    assert!(events.iter().any(|e| matches!(
+        e,
+        GameEvent::PlayerDied {
+            player: 1,
+            killer: 0,
+            ..
+        }
+    )));
+    let p = player(&gs, 0);
+    assert!(p.alive() && !p.out() && p.invulnerable());
+    assert!(!on_border(p.pnt));
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, GameEvent::RoundOver { .. })));
+
+    // The victim takes over the outer wall.
+    step_until(&mut gs, &idle, |gs| player(gs, 1).ghost());
+}
+
+#[test]
+fn bot_ghost_rides_to_a_rival_and_throws_a_bomb() {
+    let mut gs = revenge();
+    gs.bombs_mut().push(Bomb::new(0, 780, 660, 1));
+    let idle = KeyState::new();
+    step_until(&mut gs, &idle, |gs| player(gs, 0).ghost());
+
+    // Player 2 at the top-right (13, 1) is the nearest rival.
+    for _ in 0..600 {
+        let keys = bot::think(&gs, 0);
+        gs.step_with(&[keys]);
+        assert!(on_border(player(&gs, 0).pnt), "{:?}", player(&gs, 0).pnt);
+        if gs.bombs().iter().any(|b| b.owner_id == 0) {
+            break;
+        }
+    }
+    let bombs = gs.bombs();
+    let bomb = bombs
+        .iter()
+        .find(|b| b.owner_id == 0)
+        .expect("no bomb thrown");
+    let d = bomb.pnt - pnt!(780, 60);
+    assert!(d.x.abs() + d.y.abs() <= 3 * 60, "{:?}", bomb.pnt);
+}
@@ -0,0 +1,98 @@
+//! Rollback netcode over a loopback transport with latency
+
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::*;
+use bomberhuman::rollback::*;
+
+/// Scripted keys of `player` at `tick`
+fn keys(tick: u32, player: u32) -> KeyState {
+    let mut ks = KeyState::new();
+    if tick < 200 {
+        let key = match (tick / 20 + player) % 4 {
+            0 => Key::Down,
+            1 => Key::Right,
+            2 => Key::Up,
+            _ => Key::Left,
+        };
+        ks.toggle(key, true);
+        ks.toggle(Key::Button1, tick % 30 == 0);
+    }
+    ks
+}
+
+#[test]
+fn peers_converge_to_the_true_game() {
+    let mut truth = GameState::with_seed(900, 780, 5);
+    let mut peers = [
+        Rollback::new(GameState::with_seed(900, 780, 5), 0, 2),
+        Rollback::new(GameState::with_seed(900, 780, 5), 1, 2),
+    ];
+    let mut net = Loopback::new(4);
+
+    for tick in 0..260 {
+        truth.step_with(&[keys(tick, 0), keys(tick, 1)]);
+
+        for (i, peer) in peers.iter_mut().enumerate() {
+            for data in net.receive(i) {
+                peer.net_receive(&data).unwrap();
+            }
+            peer.add_local_input(keys(tick, i as u32).to_bits());
+            assert!(peer.advance(), "stalled at {}", tick);
+            while let Some(data) = peer.net_poll() {
+                net.send(1 - i, data);
+            }
+        }
+        net.tick();
+    }
+
+    assert_eq!(peers[0].tick(), truth.tick());
+    assert_eq!(peers[0].state_hash(), truth.state_hash());
+    assert_eq!(peers[1].state_hash(), truth.state_hash());
+}
+
+#[test]
+fn stalls_without_remote_inputs() {
+    let mut peer = Rollback::new(GameState::with_seed(900, 780, 5), 0, 2);
+    let mut ticks = 0;
+    while peer.advance() {
+        ticks += 1;
+    }
+    assert_eq!(ticks, MAX_ROLLBACK_TICKS);
+
+    peer.leave(1);
+    assert!(peer.advance());
+}
+
+#[test]
+fn rollback_keeps_the_atlas_and_reports_events_once() {
+    let mut peer = Rollback::new(GameState::with_seed(900, 780, 5), 0, 2);
+    let json = r#"{ "Block": { "row": 12, "animations": {
+        "hard": { "frames": [3, 3] },
+        "soft": { "frames": [4, 4] } } } }"#;
+    peer.game().load_atlas(json).unwrap();
+
+    // Player 1 starts walking at tick 3, known 4 ticks later, while
+    // player 0 puts a bomb at tick 5 in between.
+    let mut events = vec![];
+    for tick in 0..20 {
+        if tick >= 4 {
+            let mut ks = KeyState::new();
+            ks.toggle(Key::Down, tick - 4 >= 3);
+            peer.add_remote_input(tick - 4, 1, ks.to_bits());
+        }
+        let mut ks = KeyState::new();
+        ks.toggle(Key::Button1, tick == 5);
+        peer.add_local_input(ks.to_bits());
+        assert!(peer.advance());
+        events.extend(peer.game().take_events());
+    }
+
+    let placed = events
+        .iter()
+        .filter(|e| matches!(e, GameEvent::BombPlaced { player: 0, .. }))
+        .count();
+    assert_eq!(placed, 1, "{:?}", events);
+    let list = peer.game().draw_list();
+    assert!(!list.is_empty() && list.iter().all(|c| c.row == 12));
+}
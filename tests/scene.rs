@@ -0,0 +1,89 @@
+//! Scenes driven by key inputs, and bots
+
+use bomberhuman::bot;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::{Key, KeyState};
+use bomberhuman::rules::Rules;
+use bomberhuman::scene::{App, Scene};
+
+/// Press and release `key` of `bind`
+fn press(app: &mut App, bind: u32, key: Key) {
+    app.toggle_key(bind, key, true).unwrap();
+    app.update(16);
+    app.toggle_key(bind, key, false).unwrap();
+    app.update(16);
+}
+
+/// Go through the menus with the defaults of 1 human and 1 bot
+fn start_match(app: &mut App) {
+    press(app, 0, Key::Start);
+    assert_eq!(app.scene(), Scene::PlayerSelect);
+    press(app, 1, Key::Down);
+    press(app, 1, Key::Down);
+    press(app, 1, Key::Button1);
+    assert_eq!(app.scene(), Scene::Settings);
+    // START is next to BACK at the bottom.
+    press(app, 0, Key::Up);
+    press(app, 0, Key::Up);
+    press(app, 0, Key::Button1);
+}
+
+#[test]
+fn menus_lead_to_a_match() {
+    let mut app = App::with_seed(900, 780, 1);
+    assert_eq!(app.scene(), Scene::Title);
+    start_match(&mut app);
+
+    assert_eq!(app.scene(), Scene::Match);
+    assert_eq!(app.game().rules().players, 2);
+    assert_eq!(app.game().players().len(), 2);
+}
+
+#[test]
+fn pause_freezes_the_match() {
+    let mut app = App::with_seed(900, 780, 1);
+    start_match(&mut app);
+    app.update(160);
+    assert!(app.game().tick() > 0);
+
+    press(&mut app, 0, Key::Start);
+    assert_eq!(app.scene(), Scene::Pause);
+    let tick = app.game().tick();
+    for _ in 0..10 {
+        app.update(16);
+    }
+    assert_eq!(app.game().tick(), tick);
+
+    press(&mut app, 0, Key::Start);
+    assert_eq!(app.scene(), Scene::Match);
+    app.update(16);
+    assert!(app.game().tick() > tick);
+}
+
+#[test]
+fn bots_blow_up_blocks_and_survive_their_bombs() {
+    let rules = Rules {
+        players: 2,
+        ..Rules::default()
+    };
+    let mut gs = GameState::with_rules(900, 780, 1, rules);
+    let mut events = vec![];
+    for _ in 0..1000 {
+        let keys: Vec<KeyState> = (0..4).map(|p| bot::think(&gs, p)).collect();
+        gs.step_with(&keys);
+        events.append(&mut gs.take_events());
+    }
+
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, GameEvent::BlockDestroyed { .. })));
+    assert!(
+        gs.players().iter().all(|p| p.alive()),
+        "{:?}",
+        events
+            .iter()
+            .filter(|e| !matches!(e, GameEvent::BlockDestroyed { .. }))
+            .collect::<Vec<_>>()
+    );
+}
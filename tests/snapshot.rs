@@ -0,0 +1,36 @@
+//! Snapshot and restore of GameState
+
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::Key;
+
+#[test]
+fn restored_game_continues_identically() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    gs.toggle_key(0, Key::Right, true).unwrap();
+    gs.toggle_key(0, Key::Button1, true).unwrap();
+    for _ in 0..30 {
+        gs.update(16);
+    }
+    let saved = gs.snapshot();
+
+    let mut resumed = GameState::from_snapshot(&saved).unwrap();
+    assert_eq!(resumed.snapshot(), saved);
+
+    for _ in 0..300 {
+        gs.update(16);
+        resumed.update(16);
+    }
+    assert_eq!(resumed.snapshot(), gs.snapshot());
+
+    gs.restore(&saved).unwrap();
+    assert_eq!(gs.tick(), 30);
+}
+
+#[test]
+fn broken_snapshot_is_rejected() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    let saved = gs.snapshot();
+    assert!(gs.restore(&saved[..saved.len() / 2]).is_err());
+    assert!(gs.restore(&[]).is_err());
+    assert_eq!(gs.snapshot(), saved);
+}
@@ -0,0 +1,42 @@
+//! Hash of GameState for desync detection
+
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::Key;
+
+#[test]
+fn same_inputs_give_same_hashes() {
+    let mut a = GameState::with_seed(900, 780, 7);
+    let mut b = GameState::with_seed(900, 780, 7);
+    for tick in 0..200 {
+        let pressed = tick % 50 < 25;
+        for gs in [&mut a, &mut b].iter_mut() {
+            gs.toggle_key(0, Key::Down, pressed).unwrap();
+            gs.toggle_key(0, Key::Button1, true).unwrap();
+            gs.update(16);
+        }
+        assert_eq!(a.state_hash(), b.state_hash(), "diverged at {}", tick);
+    }
+
+    b.toggle_key(1, Key::Right, true).unwrap();
+    b.update(16);
+    a.update(16);
+    assert_ne!(a.state_hash(), b.state_hash());
+}
+
+#[test]
+fn order_of_actors_does_not_change_the_hash() {
+    let mut gs = GameState::with_seed(900, 780, 7);
+    for p in 0..4 {
+        gs.toggle_key(p, Key::Button1, true).unwrap();
+    }
+    gs.update(16);
+    assert!(gs.bombs().len() > 1);
+
+    let hash = gs.state_hash();
+    gs.players_mut().reverse();
+    assert_eq!(gs.state_hash(), hash);
+    gs.bombs_mut().rotate_left(1);
+    assert_eq!(gs.state_hash(), hash);
+    gs.players_mut().swap(0, 2);
+    assert_eq!(gs.state_hash(), hash);
+}
@@ -0,0 +1,92 @@
+//! Team battles and friendly fire
+
+#[macro_use]
+extern crate bomberhuman;
+
+use bomberhuman::actors::bomb::Bomb;
+use bomberhuman::events::GameEvent;
+use bomberhuman::game_state::GameState;
+use bomberhuman::geometry::*;
+use bomberhuman::keyboard::KeyState;
+use bomberhuman::rules::Rules;
+
+mod common;
+
+/// Empty arena of players 0 and 1 against 2 at `spots` with a bomb of
+/// player 0 at `bomb`
+fn arena(friendly_fire: bool, spots: [PixelPos; 3], bomb: PixelPos) -> GameState {
+    let rules = Rules {
+        players: 3,
+        teams: vec![0, 0, 1],
+        friendly_fire,
+        ..Rules::default()
+    };
+    let gs = common::open_game(rules);
+    for p in gs.players_mut().iter_mut() {
+        p.pnt = spots[p.id as usize];
+    }
+    gs.blocks_mut().clear();
+    gs.bombs_mut().push(Bomb::new(0, bomb.x, bomb.y, 2));
+    gs
+}
+
+fn alive(gs: &GameState, id: u32) -> bool {
+    gs.players().iter().any(|p| p.id == id && p.alive())
+}
+
+#[test]
+fn friendly_fire_decides_whether_teammates_get_hurt() {
+    let spots = [pnt!(600, 600), pnt!(180, 120), pnt!(120, 180)];
+    let mut gs = arena(false, spots, pnt!(120, 120));
+    let mut events = vec![];
+    for _ in 0..320 {
+        gs.step_with(&[]);
+        events.extend(gs.take_events());
+    }
+    assert!(alive(&gs, 1));
+    assert!(!alive(&gs, 2));
+    assert!(events.contains(&GameEvent::RoundOver { winner: Some(0) }));
+    assert_eq!((gs.wins(0), gs.wins(1), gs.wins(2)), (1, 1, 0));
+
+    let mut gs = arena(true, spots, pnt!(120, 120));
+    for _ in 0..320 {
+        gs.step_with(&[]);
+    }
+    assert!(!alive(&gs, 1));
+    assert!(!alive(&gs, 2));
+}
+
+#[test]
+fn teammates_walk_through_each_others_bombs() {
+    let spots = [pnt!(600, 600), pnt!(60, 60), pnt!(60, 240)];
+    let mut gs = arena(true, spots, pnt!(120, 60));
+    gs.bombs_mut().push(Bomb::new(0, 120, 240, 2));
+
+    let mut right = KeyState::new();
+    right.right = true;
+    let keys = [KeyState::new(), right.clone(), right];
+    for _ in 0..40 {
+        gs.step_with(&keys);
+    }
+    let players = gs.players();
+    let pnt = |id: u32| players.iter().find(|p| p.id == id).unwrap().pnt;
+    assert!(pnt(1).x > 120, "{:?}", pnt(1));
+    assert_eq!(pnt(2), pnt!(60, 240));
+}
+
+#[test]
+fn only_players_in_the_match_win_with_the_team() {
+    let mut rules = Rules {
+        players: 4,
+        ..Rules::default()
+    };
+    rules.set_teams(2);
+    let mut gs = GameState::with_rules(900, 780, 1, rules);
+    gs.players_mut().retain(|p| p.id % 2 == 0);
+    gs.step_with(&[]);
+    assert!(gs
+        .take_events()
+        .contains(&GameEvent::RoundOver { winner: Some(0) }));
+    let wins: Vec<u32> = (0..8).map(|p| gs.wins(p)).collect();
+    assert_eq!(wins, vec![1, 0, 1, 0, 0, 0, 0, 0]);
+}
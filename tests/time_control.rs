@@ -0,0 +1,60 @@
+//! Pause, frame-step and time scaling
+
+use bomberhuman::game_state::GameState;
+use bomberhuman::keyboard::Key;
+
+#[test]
+fn pause_stops_update_and_step_tick_advances_one() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    gs.set_paused(true);
+    gs.update(1000);
+    assert_eq!(gs.tick(), 0);
+
+    assert!(gs.step_tick());
+    assert_eq!(gs.tick(), 1);
+
+    // Time spent while paused is not caught up later.
+    gs.set_paused(false);
+    gs.update(16);
+    assert_eq!(gs.tick(), 2);
+}
+
+#[test]
+fn time_scale_changes_ticks_per_update() {
+    let mut gs = GameState::with_seed(900, 780, 1);
+    gs.set_time_scale(4.0);
+    gs.update(16);
+    assert_eq!(gs.tick(), 4);
+
+    gs.set_time_scale(0.25);
+    for _ in 0..3 {
+        gs.update(16);
+    }
+    assert_eq!(gs.tick(), 4);
+    gs.update(16);
+    assert_eq!(gs.tick(), 5);
+
+    gs.set_time_scale(100.0);
+    assert_eq!(gs.time_scale(), 4.0);
+}
+
+#[test]
+fn time_scale_keeps_simulation_deterministic() {
+    let mut slow = GameState::with_seed(900, 780, 7);
+    let mut fast = GameState::with_seed(900, 780, 7);
+    slow.set_time_scale(0.25);
+    fast.set_time_scale(4.0);
+    for gs in [&mut slow, &mut fast] {
+        gs.toggle_key(0, Key::Button1, true).unwrap();
+        gs.toggle_key(1, Key::Left, true).unwrap();
+    }
+
+    while slow.tick() < 200 {
+        slow.update(17);
+    }
+    while fast.tick() < slow.tick() {
+        fast.update(1);
+    }
+    assert_eq!(fast.tick(), slow.tick());
+    assert_eq!(fast.state_hash(), slow.state_hash());
+}
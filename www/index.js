@@ -1,47 +1,53 @@
 const debug = true;
 
-import { GameState, Key } from "bomberhuman";
+import { App, AudioEngine, GameState, InputMap, Key } from "bomberhuman";
 
 ////////////////////////////////////////////////////////////////
 // Key handling
 ////////////////////////////////////////////////////////////////
 
-/// `Key` is imported from WASM
-const KeyBind = {
-  // Player 1
-  "ArrowLeft":  [0, Key.Left],
-  "ArrowRight": [0, Key.Right],
-  "ArrowUp":    [0, Key.Up],
-  "ArrowDown":  [0, Key.Down],
-  " ":          [0, Key.Button1],
-
-  // Player 2
-  "a":          [1, Key.Left],
-  "d":          [1, Key.Right],
-  "w":          [1, Key.Up],
-  "s":          [1, Key.Down],
-  "q":          [1, Key.Button1],
-
-  // Player 3
-  "h":          [2, Key.Left],
-  "l":          [2, Key.Right],
-  "k":          [2, Key.Up],
-  "j":          [2, Key.Down],
-  "u":          [2, Key.Button1],
-
-  // Player 4
-  "1":          [3, Key.Left],
-  "2":          [3, Key.Right],
-  "3":          [3, Key.Up],
-  "4":          [3, Key.Down],
-  "5":          [3, Key.Button1],
+/// Bindings are kept in `localStorage`, and can be changed from the
+/// console, e.g. `rebind("x", 0, Key.Button1)`.
+const INPUT_MAP_STORAGE = "bomberhuman.input_map";
+
+function load_input_map() {
+  const json = localStorage.getItem(INPUT_MAP_STORAGE);
+  if (json) {
+    try {
+      return InputMap.from_json(json); // WASM
+    } catch (e) {
+      console.log("Using the default key bindings:", e);
+    }
+  }
+  return InputMap.new(); // WASM
+}
+
+const input_map = load_input_map();
+
+function rebind(input, player, key) {
+  input_map.rebind(input, player, key); // WASM
+  localStorage.setItem(INPUT_MAP_STORAGE, input_map.to_json());
+}
+window.rebind = rebind;
+window.Key = Key;
+
+/// Time control for debugging: pause the match with `Key.Start` first
+const DebugKeys = {
+  ".": g => g.step_tick(),
+  "[": g => g.set_time_scale(g.time_scale() / 2),
+  "]": g => g.set_time_scale(g.time_scale() * 2),
 };
 
 function process_key(key, state) {
   if (debug) console.log(key);
-  let bind = KeyBind[key];
+  start_audio();
+  if (debug && state && DebugKeys[key]) {
+    DebugKeys[key](game);
+    return;
+  }
+  const bind = input_map.lookup(key); // WASM
   if (bind)
-    gs.toggle_key(bind[0], bind[1], state);
+    game.toggle_key(bind.player, bind.key, state);
 }
 
 ////////////////////////////////////////////////////////////////
@@ -49,13 +55,31 @@ function process_key(key, state) {
 ////////////////////////////////////////////////////////////////
 
 // Needs "gamepadconnected" handler even if empty.
+// In local play, a new gamepad joins as the player of its index from
+// the next round, and leaves when disconnected.
 function init_gamepads(gp) {
   if (debug) {
     console.log("Gamepad connected at index:%d buttons:%d axes:%d [%s]",
                 gp.index, gp.buttons.length, gp.axes.length, gp.id);
   }
+  if (game instanceof App) join_or_leave(() => game.join(gp.index)); // WASM
+}
+
+function drop_gamepad(gp) {
+  if (game instanceof App) join_or_leave(() => game.leave(gp.index)); // WASM
+}
+
+function join_or_leave(f) {
+  try {
+    f();
+  } catch (e) {
+    console.log("Gamepad ignored:", e);
+  }
 }
 
+// Players bound to gamepads beyond it are not in the game.
+const MAX_PLAYERS = 8;
+
 function scan_gamepads() {
   // Chrome should refresh gamepads everytime you read.
   var gamepads = navigator.getGamepads ? navigator.getGamepads() : [];
@@ -63,22 +87,142 @@ function scan_gamepads() {
   for (var i = 0; i < gamepads.length; i++) {
     var pad = gamepads[i];
 
-    if (pad) {
+    if (pad && pad.index < MAX_PLAYERS) {
+      // Pads may have fewer buttons than the standard mapping.
+      const pressed = (b) => !!(pad.buttons[b] && pad.buttons[b].pressed);
       // Send state to WASM
-      gs.toggle_key(i, Key.Left,    pad.axes[0] < -0.5);
-      gs.toggle_key(i, Key.Right,   pad.axes[0] >  0.5);
-      gs.toggle_key(i, Key.Up,      pad.axes[1] < -0.5);
-      gs.toggle_key(i, Key.Down,    pad.axes[1] >  0.5);
-      gs.toggle_key(i, Key.Button1, pad.buttons[0].pressed);
+      game.set_axis(i, pad.axes[0], pad.axes[1]);
+      game.toggle_key(i, Key.Button1, pressed(0));
+      game.toggle_key(i, Key.Start,   pressed(9));
     }
   }
 }
 
+////////////////////////////////////////////////////////////////
+// Virtual joystick for phones and tablets
+////////////////////////////////////////////////////////////////
+
+// Touch on the left half of the screen becomes the stick of P1
+// centered at the first touch, and the right half becomes Button1.
+const STICK_RADIUS = 60;
+let stick = null;
+
+function touch_start(e) {
+  start_audio();
+  for (const t of e.changedTouches) {
+    if (t.clientX < window.innerWidth / 2) {
+      if (!stick) stick = { id: t.identifier, x: t.clientX, y: t.clientY };
+    } else {
+      game.toggle_key(0, Key.Button1, true); // WASM
+    }
+  }
+  e.preventDefault();
+}
+
+function touch_move(e) {
+  for (const t of e.changedTouches) {
+    if (stick && t.identifier === stick.id) {
+      const x = (t.clientX - stick.x) / STICK_RADIUS;
+      const y = (t.clientY - stick.y) / STICK_RADIUS;
+      game.set_axis(0, Math.max(-1, Math.min(1, x)),
+                       Math.max(-1, Math.min(1, y))); // WASM
+    }
+  }
+  e.preventDefault();
+}
+
+function touch_end(e) {
+  for (const t of e.changedTouches) {
+    if (stick && t.identifier === stick.id) {
+      stick = null;
+      game.set_axis(0, 0, 0); // WASM
+    } else if (t.clientX >= window.innerWidth / 2) {
+      game.toggle_key(0, Key.Button1, false); // WASM
+    }
+  }
+  e.preventDefault();
+}
+
+////////////////////////////////////////////////////////////////
+// Online modes
+////////////////////////////////////////////////////////////////
+
+// Lockstep mode through the relay (src/bin/relay.rs):
+//   http://localhost:9000/?relay=ws://localhost:9001
+// Remote mode on the authoritative server (src/bin/server.rs):
+//   http://localhost:9000/?server=ws://localhost:9002
+//   http://localhost:9000/?server=ws://localhost:9002/spectate
+const params = new URLSearchParams(window.location.search);
+let socket = null;
+
+function connect(url, on_open) {
+  socket = new WebSocket(url);
+  socket.binaryType = "arraybuffer";
+  socket.onopen = on_open;
+  socket.onmessage = e => game.net_receive(new Uint8Array(e.data));
+  socket.onclose = () => {
+    if (debug) console.log("Disconnected from %s", url);
+    game.net_disconnect();
+    socket = null;
+  };
+}
+
+function flush_socket() {
+  if (!socket || socket.readyState !== WebSocket.OPEN) return;
+
+  let packet;
+  while ((packet = game.net_poll()) !== undefined) {
+    socket.send(packet);
+  }
+}
+
+////////////////////////////////////////////////////////////////
+// Audio
+////////////////////////////////////////////////////////////////
+
+let audio = null;
+
+// Browsers allow audio only after the user interacts with the page.
+function start_audio() {
+  if (audio) return;
+  try {
+    audio = new AudioEngine(); // WASM
+  } catch (e) {
+    console.log("Audio is not available:", e);
+    audio = false;
+  }
+}
+
+////////////////////////////////////////////////////////////////
+// Game events
+////////////////////////////////////////////////////////////////
+
+let shake_until = 0;
+
+function handle_events(events) {
+  for (const ev of events) {
+    if (debug) console.log(ev);
+    if (ev.type === "Exploded") {
+      shake_until = performance.now() + 100 + 50 * ev.power;
+    }
+  }
+  const canvas = document.getElementById("canvas");
+  if (performance.now() < shake_until) {
+    const dx = Math.round(Math.random() * 6 - 3);
+    const dy = Math.round(Math.random() * 6 - 3);
+    canvas.style.transform = `translate(${dx}px, ${dy}px)`;
+  } else {
+    canvas.style.transform = "";
+  }
+}
+
 ////////////////////////////////////////////////////////////////
 // Main loop
 ////////////////////////////////////////////////////////////////
 
-let gs = null;
+// `App` with menus and bots for local play, or `GameState` in the
+// online modes.
+let game = null;
 let start = null;
 let prev_timestamp = null;
 
@@ -93,8 +237,15 @@ let game_loop = (timestamp) => {
   let delta = (timestamp - prev_timestamp);
 
   scan_gamepads();
-  gs.update(delta);  // WASM
-  gs.draw();  // WASM
+  game.update(delta);  // WASM
+  flush_socket();
+  const events = game.drain_events();  // WASM
+  if (audio) {
+    audio.handle_events(events);  // WASM
+    audio.update(game.time_left());  // WASM
+  }
+  handle_events(events);
+  game.draw();  // WASM
 
   prev_timestamp = timestamp;
   requestAnimationFrame(game_loop);
@@ -105,10 +256,25 @@ let game_loop = (timestamp) => {
 ////////////////////////////////////////////////////////////////
 
 function start_game() {
-  gs = GameState.new(900, 780); // WASM
+  const online = params.get("relay") || params.get("server");
+  game = online ? GameState.new(900, 780) : App.new(900, 780); // WASM
+  fetch("assets/sprites.json")
+    .then(resp => resp.text())
+    .then(json => game.load_atlas(json))  // WASM
+    .catch(e => console.log("Using the built-in sprite atlas:", e));
   document.addEventListener('keydown', e => process_key(e.key, true));
   document.addEventListener('keyup',   e => process_key(e.key, false));
+  document.addEventListener('click', start_audio);
+  const canvas = document.getElementById("canvas");
+  const touch = { passive: false };
+  canvas.addEventListener('touchstart',  touch_start, touch);
+  canvas.addEventListener('touchmove',   touch_move,  touch);
+  canvas.addEventListener('touchend',    touch_end,   touch);
+  canvas.addEventListener('touchcancel', touch_end,   touch);
   document.addEventListener("gamepadconnected", e => init_gamepads(e.gamepad));
+  document.addEventListener("gamepaddisconnected", e => drop_gamepad(e.gamepad));
+  if (params.get("relay")) connect(params.get("relay"), () => game.net_join());
+  if (params.get("server")) connect(params.get("server"), () => game.net_watch());
   game_loop();
 }
 